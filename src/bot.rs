@@ -0,0 +1,186 @@
+use std::str::FromStr;
+
+use bevy::{prelude::*, utils::HashSet};
+use bevy_ggrs::{GgrsApp, GgrsSchedule, PlayerInputs, Rollback, RollbackOrdered};
+use itertools::Itertools;
+
+use crate::{
+    components::{Bomb, Dead, Fire, Item, Player, Position, Solid},
+    constants::{
+        BOT_MOVE_FRAME_INTERVAL, INPUT_ACTION, INPUT_DOWN, INPUT_LEFT, INPUT_RIGHT, INPUT_UP,
+    },
+    resources::{FrameCount, GameFreeze, MapSize, SessionRng, TrainingConfig},
+    systems::{increase_frame_system, player_move},
+    types::{Direction, GgrsConfig, PlayerInput},
+    utils::blast_positions,
+};
+
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BotDifficulty {
+    // wanders around randomly
+    Easy,
+    // heads towards the closest item
+    #[default]
+    Medium,
+    // heads towards the closest item while staying out of the fire
+    Hard,
+}
+
+impl FromStr for BotDifficulty {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "easy" => Ok(Self::Easy),
+            "medium" => Ok(Self::Medium),
+            "hard" => Ok(Self::Hard),
+            _ => Err(format!("unknown bot difficulty: {s}")),
+        }
+    }
+}
+
+impl BotDifficulty {
+    fn bomb_drop_chance_percentage(self) -> u64 {
+        match self {
+            Self::Easy => 10,
+            Self::Medium => 15,
+            Self::Hard => 20,
+        }
+    }
+}
+
+// the inputs of these players are generated inside the rollback schedule, so they are the same on every peer
+#[derive(Component, Clone, Copy)]
+pub struct BotPlayer;
+
+pub struct BotPlugin {
+    pub difficulty: BotDifficulty,
+}
+
+impl Plugin for BotPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(self.difficulty)
+            .rollback_component_with_copy::<BotPlayer>()
+            .add_systems(
+                GgrsSchedule,
+                (tag_bot_players, apply_deferred, bot_move_system)
+                    .chain()
+                    .after(increase_frame_system)
+                    .before(player_move),
+            );
+    }
+}
+
+// every player except the local one is a bot in training mode, unless the local player is one too
+fn tag_bot_players(
+    mut commands: Commands,
+    training_config: Res<TrainingConfig>,
+    player_query: Query<(Entity, &Player), Without<BotPlayer>>,
+) {
+    for (entity, _) in player_query
+        .iter()
+        .filter(|(_, player)| player.id.0 != 0 || training_config.bot_player)
+    {
+        commands.entity(entity).insert(BotPlayer);
+    }
+}
+
+fn direction_to_input(direction: Direction) -> u16 {
+    match direction {
+        Direction::Up => INPUT_UP,
+        Direction::Down => INPUT_DOWN,
+        Direction::Left => INPUT_LEFT,
+        Direction::Right => INPUT_RIGHT,
+    }
+}
+
+fn random_step(
+    position: Position,
+    is_passable: impl Fn(Position) -> bool,
+    map_size: MapSize,
+    rng: &mut SessionRng,
+) -> Option<Direction> {
+    let directions = Direction::LIST
+        .into_iter()
+        .filter(|&direction| {
+            position
+                .checked_offset(direction, 1)
+                .is_some_and(|p| p.in_bounds(map_size) && is_passable(p))
+        })
+        .collect_vec();
+    (!directions.is_empty()).then(|| directions[rng.gen_range(0, directions.len() as u64) as usize])
+}
+
+pub fn bot_move_system(
+    mut inputs: ResMut<PlayerInputs<GgrsConfig>>,
+    mut session_rng: ResMut<SessionRng>,
+    rollback_ordered: Res<RollbackOrdered>,
+    bot_difficulty: Res<BotDifficulty>,
+    map_size: Res<MapSize>,
+    bot_query: Query<(&Rollback, &Player, &Position), (With<BotPlayer>, Without<Dead>)>,
+    solid_query: Query<&Position, With<Solid>>,
+    item_query: Query<&Position, With<Item>>,
+    fire_query: Query<&Position, With<Fire>>,
+    bomb_query: Query<(&Bomb, &Position)>,
+    frame_count: Res<FrameCount>,
+    game_freeze: Option<Res<GameFreeze>>,
+) {
+    if game_freeze.is_some() || frame_count.frame % BOT_MOVE_FRAME_INTERVAL != 0 {
+        return;
+    }
+
+    let map_size = *map_size;
+    let solids: HashSet<Position> = solid_query.iter().copied().collect();
+    let items: HashSet<Position> = item_query.iter().copied().collect();
+    let fires: HashSet<Position> = fire_query.iter().copied().collect();
+    let danger_positions: HashSet<Position> = match *bot_difficulty {
+        BotDifficulty::Hard => bomb_query
+            .iter()
+            .flat_map(|(bomb, &bomb_position)| {
+                blast_positions(bomb_position, bomb.range, map_size, &solids)
+            })
+            .chain(fires.iter().copied())
+            .collect(),
+        _ => HashSet::new(),
+    };
+
+    // bot sorting is needed to ensure determinism of the RNG use
+    for (_, player, &position) in bot_query
+        .iter()
+        .sorted_by_cached_key(|q| rollback_ordered.order(*q.0))
+    {
+        let rng = &mut session_rng;
+        let is_free = |p: Position| !solids.contains(&p) && !fires.contains(&p);
+        let is_safe = |p: Position| is_free(p) && !danger_positions.contains(&p);
+        let is_item = |p: Position| items.contains(&p);
+        let direction = match *bot_difficulty {
+            BotDifficulty::Easy => random_step(position, is_free, map_size, rng),
+            BotDifficulty::Medium => position
+                .shortest_path(is_item, is_free, map_size, rng)
+                .or_else(|| random_step(position, is_free, map_size, rng)),
+            BotDifficulty::Hard => {
+                if danger_positions.contains(&position) {
+                    position.shortest_path(
+                        |p| !danger_positions.contains(&p),
+                        is_free,
+                        map_size,
+                        rng,
+                    )
+                } else {
+                    position
+                        .shortest_path(is_item, is_safe, map_size, rng)
+                        .or_else(|| random_step(position, is_safe, map_size, rng))
+                }
+            }
+        };
+
+        let mut input = direction.map(direction_to_input).unwrap_or(0);
+        // hard bots don't drop bombs while fleeing
+        if !danger_positions.contains(&position)
+            && session_rng.gen_bool(bot_difficulty.bomb_drop_chance_percentage(), 100)
+        {
+            input |= INPUT_ACTION;
+        }
+        inputs[player.id.0 as usize].0 = PlayerInput(input);
+    }
+}
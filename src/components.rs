@@ -1,10 +1,49 @@
-use bevy::{ecs as bevy_ecs, prelude::Component, render::color::Color};
+use std::{
+    collections::VecDeque,
+    hash::{Hash, Hasher},
+};
 
-use crate::types::{Direction, PlayerID};
+use bevy::{ecs as bevy_ecs, math::Vec3, prelude::Component, render::color::Color, utils::HashMap};
+
+use crate::{
+    resources::{MapSize, SessionRng},
+    types::{Direction, PlayerID},
+    utils::shuffle,
+};
 
 #[derive(Component)]
 pub struct FullscreenMessageText;
 
+#[derive(Component)]
+pub struct LoadingProgressBar;
+
+// uses rendered frames since fullscreen messages are also shown outside of the game session
+#[derive(Component)]
+pub struct FadeIn {
+    pub start_frame: u32,
+    pub duration_frames: u32,
+}
+
+// Lobby display
+
+#[derive(Component)]
+pub struct LobbyPortrait(pub usize);
+
+#[derive(Component)]
+pub struct LobbyConnectedCounter;
+
+#[derive(Component)]
+pub struct RetryButton;
+
+#[derive(Component)]
+pub struct RoomUrlDisplay {
+    // rendered frame until which the copy confirmation is shown
+    pub copied_message_end_frame: Option<u32>,
+}
+
+#[derive(Component)]
+pub struct AutoRetryCountdown;
+
 // HUD display
 
 #[derive(Component)]
@@ -19,6 +58,16 @@ pub struct HUDRoot;
 #[derive(Component)]
 pub struct GameTimerDisplay;
 
+#[derive(Component)]
+pub struct WallCountDisplay;
+
+#[derive(Component)]
+pub struct WallOfDeathOverlayCell(pub Position);
+
+// not rollback registered, respawned on every frame
+#[derive(Component)]
+pub struct BombWarningOverlay;
+
 #[derive(Component)]
 pub struct NetworkStatsDisplay;
 
@@ -28,6 +77,43 @@ pub struct PlayerPortraitDisplay;
 #[derive(Component)]
 pub struct PlayerPortrait(pub PlayerID);
 
+#[derive(Component)]
+pub struct PlayerHealthBar(pub PlayerID);
+
+#[derive(Component)]
+pub struct FreezeMessageDisplay;
+
+// not rollback registered, only used to smooth out rendering
+#[derive(Component)]
+pub struct InterpolatedTransform {
+    // translation the current interpolation starts from
+    pub previous: Vec3,
+    pub rendered: Vec3,
+}
+
+#[derive(Component)]
+pub struct RollbackDebugDisplay;
+
+// not rollback registered, uses rendered frames
+#[derive(Component)]
+pub struct RollbackGhost {
+    pub expiration_frame: u32,
+}
+
+#[derive(Component)]
+pub struct CameraShake {
+    pub origin: Vec3,
+    // maximum offset in pixels, decays over time
+    pub intensity: f32,
+}
+
+#[derive(Component)]
+pub struct ConfettiParticle {
+    pub fall_speed: f32,
+    pub drift: f32,
+    pub rotation_speed: f32,
+}
+
 #[derive(Component)]
 pub struct LeaderboardUIRoot;
 
@@ -36,17 +122,75 @@ pub struct LeaderboardUIContent;
 
 // In-game
 
-#[derive(Component, Clone, Copy, Hash)]
+#[derive(Component, Clone, Copy)]
 pub struct Player {
     pub id: PlayerID,
     pub can_push_bombs: bool,
+    pub magnet_radius: u8,
+    // kept when the player stops moving
+    pub last_move_direction: Option<Direction>,
+    // frames between two moves, lowered by speed ups
+    pub move_cooldown_frames: u32,
+    pub next_move_frame: u32,
+    // the last direction pressed, kept until the player is allowed to move again
+    pub pending_move: Option<Direction>,
+    // movement and bomb placement are blocked before this frame, 0 when not stunned
+    pub stunned_until_frame: u32,
+    // `None` in free-for-all matches
+    pub team: Option<u8>,
+}
+
+impl Player {
+    pub fn can_move(&self, frame: u32) -> bool {
+        frame >= self.next_move_frame && !self.is_stunned(frame)
+    }
+
+    pub fn is_stunned(&self, frame: u32) -> bool {
+        frame < self.stunned_until_frame
+    }
+}
+
+impl Hash for Player {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+        self.can_push_bombs.hash(state);
+        self.magnet_radius.hash(state);
+        hash_option(&self.last_move_direction, state);
+        self.move_cooldown_frames.hash(state);
+        self.next_move_frame.hash(state);
+        hash_option(&self.pending_move, state);
+        self.stunned_until_frame.hash(state);
+        hash_option(&self.team, state);
+    }
 }
 
+#[derive(Component, Clone, Copy, Hash)]
+pub struct PlayerHP {
+    pub current: u8,
+    pub max: u8,
+}
+
+// frame until which the player can't get hurt again
+#[derive(Component, Clone, Copy)]
+pub struct InvulnFrames(pub u32);
+
 #[derive(Component, Clone, Copy)]
 pub struct Dead {
     pub cleanup_frame: u32,
 }
 
+#[derive(Component, Clone, Copy)]
+pub struct VictoryDance {
+    // frames elapsed since the dance started
+    pub blink_frame: u8,
+}
+
+#[derive(Component, Clone, Copy)]
+pub struct DeathAnimation {
+    // frames elapsed since the death
+    pub blink_frame: u8,
+}
+
 #[derive(Component, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Position {
     pub y: u8,
@@ -54,22 +198,170 @@ pub struct Position {
 }
 
 impl Position {
-    pub fn offset(&self, direction: Direction, distance: u8) -> Self {
+    // returns `None` when moving past the edge of the coordinate space, the map bounds still need checking
+    pub fn checked_offset(&self, direction: Direction, distance: u8) -> Option<Self> {
         let (new_y, new_x) = match direction {
-            Direction::Right => (self.y, self.x + distance),
-            Direction::Down => (self.y + distance, self.x),
-            Direction::Left => (self.y, self.x - distance),
-            Direction::Up => (self.y - distance, self.x),
+            Direction::Right => (self.y, self.x.checked_add(distance)?),
+            Direction::Down => (self.y.checked_add(distance)?, self.x),
+            Direction::Left => (self.y, self.x.checked_sub(distance)?),
+            Direction::Up => (self.y.checked_sub(distance)?, self.x),
         };
 
-        Position { y: new_y, x: new_x }
+        Some(Position { y: new_y, x: new_x })
     }
+
+    pub fn in_bounds(&self, map_size: MapSize) -> bool {
+        self.y < map_size.rows && self.x < map_size.columns
+    }
+
+    pub fn manhattan_distance(&self, other: &Position) -> u32 {
+        self.y.abs_diff(other.y) as u32 + self.x.abs_diff(other.x) as u32
+    }
+
+    // in the order of `Direction::LIST`, `None` past the edge of the coordinate space
+    pub fn neighbors(&self) -> [Option<Position>; 4] {
+        Direction::LIST.map(|direction| self.checked_offset(direction, 1))
+    }
+
+    // the next `count` positions in the direction, excluding this one
+    pub fn iter_in_direction(&self, direction: Direction, count: usize) -> DirectionIter {
+        DirectionIter {
+            position: *self,
+            direction,
+            remaining: count,
+        }
+    }
+
+    // breadth-first search for the closest goal position, returns the first step towards it
+    // the direction order is shuffled so that ties between equally short paths are broken deterministically
+    pub fn shortest_path(
+        &self,
+        is_goal: impl Fn(Position) -> bool,
+        is_passable: impl Fn(Position) -> bool,
+        map_size: MapSize,
+        rng: &mut SessionRng,
+    ) -> Option<Direction> {
+        let mut directions = Direction::LIST;
+        shuffle(&mut directions, rng);
+
+        let mut first_steps: HashMap<Position, Option<Direction>> = HashMap::new();
+        first_steps.insert(*self, None);
+        let mut queue = VecDeque::from([*self]);
+        while let Some(position) = queue.pop_front() {
+            if is_goal(position) {
+                return first_steps[&position];
+            }
+
+            for direction in directions {
+                let Some(next_position) = position
+                    .checked_offset(direction, 1)
+                    .filter(|p| p.in_bounds(map_size) && is_passable(*p))
+                else {
+                    continue;
+                };
+                if first_steps.contains_key(&next_position) {
+                    continue;
+                }
+
+                let first_step = first_steps[&position].or(Some(direction));
+                first_steps.insert(next_position, first_step);
+                queue.push_back(next_position);
+            }
+        }
+
+        None
+    }
+}
+
+// writes the `Some` or `None` as a u8 ahead of the value
+pub fn hash_option<T: Hash, H: Hasher>(value: &Option<T>, state: &mut H) {
+    match value {
+        Some(value) => {
+            1u8.hash(state);
+            value.hash(state);
+        }
+        None => 0u8.hash(state),
+    }
+}
+
+// stops early at the edge of the coordinate space, the map bounds still need checking
+pub struct DirectionIter {
+    position: Position,
+    direction: Direction,
+    remaining: usize,
+}
+
+impl Iterator for DirectionIter {
+    type Item = Position;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+
+        match self.position.checked_offset(self.direction, 1) {
+            Some(position) => {
+                self.position = position;
+                self.remaining -= 1;
+                Some(position)
+            }
+            None => {
+                self.remaining = 0;
+                None
+            }
+        }
+    }
+}
+
+#[derive(Component, Clone, Copy)]
+pub struct Emote {
+    // index into `EMOTE_SYMBOLS`
+    pub symbol: u8,
+    pub shown_frame: u32,
+    pub expiration_frame: u32,
+}
+
+#[derive(Component, Clone, Copy)]
+pub struct BombCountOverlay;
+
+#[derive(Component)]
+pub struct WaterTile;
+
+#[derive(Component, Clone, Copy)]
+pub struct CrackableIce {
+    // steps left until the ice breaks
+    pub hp: u8,
+}
+
+#[derive(Component, Clone, Copy)]
+pub struct Slowed {
+    // multiplies the movement interval
+    pub move_frame_delay: u32,
+    pub expires_frame: u32,
+}
+
+#[derive(Component, Clone, Copy)]
+pub struct HeldAction {
+    pub frames_held: u32,
+}
+
+// not rollback registered, previews the bomb position while the action input is held
+#[derive(Component)]
+pub struct BombGhost(pub PlayerID);
+
+#[derive(Component, Clone, Copy)]
+pub struct PendingBombDrop {
+    pub expires_frame: u32,
 }
 
 #[derive(Component, Clone, Copy, Hash)]
 pub struct BombSatchel {
     pub bombs_available: u8,
     pub bomb_range: u8,
+    // the next bomb placed is a remote one, reset once it detonates
+    pub has_remote_detonator: bool,
+    // every bomb placed pierces walls
+    pub has_pierce: bool,
 }
 
 #[derive(Component, Clone, Copy)]
@@ -77,8 +369,30 @@ pub struct Bomb {
     pub owner: Option<PlayerID>,
     pub range: u8,
     pub expiration_frame: u32,
+    // can be detonated early by its owner
+    pub is_remote: bool,
+    // the fire continues past the first destructible wall in each direction
+    pub pierces_walls: bool,
+}
+
+impl Hash for Bomb {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        hash_option(&self.owner, state);
+        self.range.hash(state);
+        self.expiration_frame.hash(state);
+        self.is_remote.hash(state);
+        self.pierces_walls.hash(state);
+    }
 }
 
+// how many chain reaction steps led to the bomb's detonation
+#[derive(Component, Clone, Copy, Default)]
+pub struct ChainDepth(pub u8);
+
+// owner of the bomb that started the chain reaction
+#[derive(Component, Clone, Copy)]
+pub struct ChainInitiator(pub Option<PlayerID>);
+
 #[derive(Component, Clone, Copy)]
 pub struct Moving {
     pub direction: Direction,
@@ -95,6 +409,15 @@ pub struct Fuse {
 #[derive(Component, Clone, Copy)]
 pub struct Fire {
     pub expiration_frame: u32,
+    // the player whose bomb spawned the fire, used to spare their teammates
+    pub owner: Option<PlayerID>,
+}
+
+impl Hash for Fire {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.expiration_frame.hash(state);
+        hash_option(&self.owner, state);
+    }
 }
 
 #[derive(Component, Clone, Copy)]
@@ -112,13 +435,310 @@ pub struct Crumbling {
 }
 
 #[derive(Component, Debug, Clone, Copy)]
+#[cfg_attr(
+    not(target_arch = "wasm32"),
+    derive(serde::Deserialize, serde::Serialize)
+)]
 pub enum Item {
     BombsUp,
     RangeUp,
     BombPush,
+    SpeedUp,
+    // curses
+    BombDown,
+    RangeDown,
+    Skull,
+    RemoteBomb,
+    Pierce,
+    Magnet,
+}
+
+impl Item {
+    pub fn is_curse(&self) -> bool {
+        matches!(self, Item::BombDown | Item::RangeDown | Item::Skull)
+    }
 }
 
 #[derive(Component, Clone, Copy)]
 pub struct BurningItem {
     pub expiration_frame: u32,
+    // number of chain burn hops from the item that was directly burned
+    pub hop: u8,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::hash_map::DefaultHasher;
+
+    use super::*;
+
+    fn hash_of(value: &impl Hash) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn test_bomb_hash_includes_owner() {
+        let bomb = Bomb {
+            owner: Some(PlayerID(0)),
+            range: 2,
+            expiration_frame: 60,
+            is_remote: false,
+            pierces_walls: false,
+        };
+        let other_owner_bomb = Bomb {
+            owner: Some(PlayerID(1)),
+            ..bomb
+        };
+        let ownerless_bomb = Bomb {
+            owner: None,
+            ..bomb
+        };
+
+        assert_ne!(hash_of(&bomb), hash_of(&other_owner_bomb));
+        assert_ne!(hash_of(&bomb), hash_of(&ownerless_bomb));
+        let same_bomb = bomb;
+        assert_eq!(hash_of(&bomb), hash_of(&same_bomb));
+    }
+
+    #[test]
+    fn test_player_hash_includes_last_move_direction() {
+        let player = Player {
+            id: PlayerID(0),
+            can_push_bombs: false,
+            magnet_radius: 0,
+            last_move_direction: Some(Direction::Up),
+            move_cooldown_frames: 1,
+            next_move_frame: 0,
+            pending_move: None,
+            stunned_until_frame: 0,
+            team: None,
+        };
+        let other_direction_player = Player {
+            last_move_direction: Some(Direction::Down),
+            ..player
+        };
+        let unmoved_player = Player {
+            last_move_direction: None,
+            ..player
+        };
+
+        assert_ne!(hash_of(&player), hash_of(&other_direction_player));
+        assert_ne!(hash_of(&player), hash_of(&unmoved_player));
+    }
+
+    #[test]
+    fn test_player_move_cooldown() {
+        let player = Player {
+            id: PlayerID(0),
+            can_push_bombs: false,
+            magnet_radius: 0,
+            last_move_direction: None,
+            move_cooldown_frames: 2,
+            next_move_frame: 2,
+            pending_move: Some(Direction::Up),
+            stunned_until_frame: 4,
+            team: None,
+        };
+
+        let moving_frames = (0..6)
+            .filter(|&frame| player.can_move(frame))
+            .collect::<Vec<_>>();
+        assert_eq!(moving_frames, vec![4, 5]);
+    }
+
+    #[test]
+    fn test_position_shortest_path() {
+        let map_size = MapSize {
+            rows: 5,
+            columns: 5,
+        };
+        let mut rng = SessionRng::new(0);
+        let start = Position { y: 2, x: 1 };
+        let goal = Position { y: 2, x: 4 };
+        // a wall in the middle column with a single gap at the top
+        let blocked = [
+            Position { y: 1, x: 2 },
+            Position { y: 2, x: 2 },
+            Position { y: 3, x: 2 },
+            Position { y: 4, x: 2 },
+        ];
+        let is_passable = |p: Position| !blocked.contains(&p);
+
+        assert_eq!(
+            start.shortest_path(|p| p == goal, is_passable, map_size, &mut rng),
+            Some(Direction::Up)
+        );
+        // already at the goal
+        assert_eq!(
+            goal.shortest_path(|p| p == goal, is_passable, map_size, &mut rng),
+            None
+        );
+        // unreachable
+        assert_eq!(
+            start.shortest_path(|p| p == goal, |p| p.x < 2, map_size, &mut rng),
+            None
+        );
+    }
+
+    #[test]
+    fn test_position_in_bounds() {
+        let map_size = MapSize {
+            rows: 9,
+            columns: 13,
+        };
+
+        // corners
+        assert!(Position { y: 0, x: 0 }.in_bounds(map_size));
+        assert!(Position { y: 8, x: 12 }.in_bounds(map_size));
+        assert!(Position { y: 0, x: 12 }.in_bounds(map_size));
+        assert!(Position { y: 8, x: 0 }.in_bounds(map_size));
+
+        // inside
+        assert!(Position { y: 4, x: 6 }.in_bounds(map_size));
+
+        // one outside in each direction
+        assert!(!Position { y: 9, x: 6 }.in_bounds(map_size));
+        assert!(!Position { y: 4, x: 13 }.in_bounds(map_size));
+        assert!(!Position { y: 0, x: 12 }
+            .checked_offset(Direction::Right, 1)
+            .unwrap()
+            .in_bounds(map_size));
+        assert!(!Position { y: 8, x: 0 }
+            .checked_offset(Direction::Down, 1)
+            .unwrap()
+            .in_bounds(map_size));
+    }
+
+    #[test]
+    fn test_position_checked_offset_at_corners() {
+        let max = u8::MAX;
+        let corners = [
+            Position { y: 0, x: 0 },
+            Position { y: 0, x: max },
+            Position { y: max, x: 0 },
+            Position { y: max, x: max },
+        ];
+
+        for corner in corners {
+            for direction in Direction::LIST {
+                let blocked = match direction {
+                    Direction::Up => corner.y == 0,
+                    Direction::Down => corner.y == max,
+                    Direction::Left => corner.x == 0,
+                    Direction::Right => corner.x == max,
+                };
+
+                let offset = corner.checked_offset(direction, 1);
+                if blocked {
+                    assert_eq!(offset, None, "{corner:?} {direction:?}");
+                } else {
+                    let offset = offset.unwrap();
+                    assert_eq!(
+                        offset.y.abs_diff(corner.y) + offset.x.abs_diff(corner.x),
+                        1,
+                        "{corner:?} {direction:?}"
+                    );
+                }
+            }
+        }
+
+        // moving further than the distance to the edge
+        assert_eq!(
+            Position { y: 1, x: 1 }.checked_offset(Direction::Up, 2),
+            None
+        );
+        assert_eq!(
+            Position { y: 1, x: 1 }.checked_offset(Direction::Right, 2),
+            Some(Position { y: 1, x: 3 })
+        );
+    }
+
+    #[test]
+    fn test_position_manhattan_distance() {
+        let position = Position { y: 3, x: 5 };
+        assert_eq!(position.manhattan_distance(&position), 0);
+        assert_eq!(position.manhattan_distance(&Position { y: 1, x: 8 }), 5);
+        assert_eq!(Position { y: 1, x: 8 }.manhattan_distance(&position), 5);
+
+        // doesn't overflow at the edges of the coordinate space
+        let max = Position {
+            y: u8::MAX,
+            x: u8::MAX,
+        };
+        assert_eq!(
+            Position { y: 0, x: 0 }.manhattan_distance(&max),
+            2 * u8::MAX as u32
+        );
+    }
+
+    #[test]
+    fn test_position_neighbors() {
+        let center = Position { y: 3, x: 5 };
+        let mut neighbors = center.neighbors().into_iter().flatten().collect::<Vec<_>>();
+        neighbors.sort();
+        assert_eq!(
+            neighbors,
+            vec![
+                Position { y: 2, x: 5 },
+                Position { y: 3, x: 4 },
+                Position { y: 3, x: 6 },
+                Position { y: 4, x: 5 },
+            ]
+        );
+
+        let corner = Position { y: 0, x: 0 };
+        let mut neighbors = corner.neighbors().into_iter().flatten().collect::<Vec<_>>();
+        neighbors.sort();
+        assert_eq!(
+            neighbors,
+            vec![Position { y: 0, x: 1 }, Position { y: 1, x: 0 }]
+        );
+
+        let edge = Position { y: 4, x: u8::MAX };
+        assert_eq!(edge.neighbors().into_iter().flatten().count(), 3);
+    }
+
+    #[test]
+    fn test_position_iter_in_direction_from_corner() {
+        let corner = Position { y: 0, x: 0 };
+        assert_eq!(
+            corner
+                .iter_in_direction(Direction::Right, 3)
+                .collect::<Vec<_>>(),
+            vec![
+                Position { y: 0, x: 1 },
+                Position { y: 0, x: 2 },
+                Position { y: 0, x: 3 },
+            ]
+        );
+        assert_eq!(
+            corner
+                .iter_in_direction(Direction::Down, 2)
+                .collect::<Vec<_>>(),
+            vec![Position { y: 1, x: 0 }, Position { y: 2, x: 0 }]
+        );
+        assert_eq!(corner.iter_in_direction(Direction::Left, 3).count(), 0);
+        assert_eq!(corner.iter_in_direction(Direction::Up, 3).count(), 0);
+        assert_eq!(corner.iter_in_direction(Direction::Right, 0).count(), 0);
+
+        // stops at the edge of the coordinate space
+        let far_corner = Position {
+            y: u8::MAX - 1,
+            x: u8::MAX - 1,
+        };
+        assert_eq!(
+            far_corner
+                .iter_in_direction(Direction::Right, 5)
+                .collect::<Vec<_>>(),
+            vec![Position {
+                y: u8::MAX - 1,
+                x: u8::MAX
+            }]
+        );
+        assert_eq!(far_corner.iter_in_direction(Direction::Down, 5).count(), 1);
+        assert_eq!(far_corner.iter_in_direction(Direction::Up, 5).count(), 5);
+        assert_eq!(far_corner.iter_in_direction(Direction::Left, 5).count(), 5);
+    }
 }
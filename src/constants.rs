@@ -26,6 +26,13 @@ pub const HUD_HEIGHT: u32 = 14 * PIXEL_SCALE;
 pub const TILE_HEIGHT: u32 = 8 * PIXEL_SCALE;
 pub const TILE_WIDTH: u32 = 6 * PIXEL_SCALE;
 
+// `COLORS` indices identifying the players, e.g. on the bomb ownership indicators
+pub const PLAYER_COLOR_INDICES: [usize; 8] = [15, 12, 9, 10, 14, 11, 13, 6];
+// `COLORS` indices of the HUD portrait borders in team matches, wrapping around for higher team numbers
+pub const TEAM_COLOR_INDICES: [usize; 4] = [12, 9, 10, 14];
+// how strongly bombs are tinted toward their owner's color
+pub const BOMB_OWNER_TINT_STRENGTH: f32 = 0.3;
+
 pub const WALL_Z_LAYER: f32 = 60.0;
 pub const PLAYER_Z_LAYER: f32 = 50.0;
 pub const BOMB_Z_LAYER: f32 = 25.0;
@@ -33,27 +40,151 @@ pub const ITEM_Z_LAYER: f32 = 20.0;
 pub const DESTRUCTIBLE_WALL_Z_LAYER: f32 = 10.0;
 pub const FIRE_Z_LAYER: f32 = 5.0;
 
-pub const INPUT_UP: u8 = 1 << 0;
-pub const INPUT_DOWN: u8 = 1 << 1;
-pub const INPUT_LEFT: u8 = 1 << 2;
-pub const INPUT_RIGHT: u8 = 1 << 3;
-pub const INPUT_ACTION: u8 = 1 << 4;
+pub const CONFETTI_COUNT: u16 = 600;
+pub const MAX_CONFETTI_PARTICLES: usize = 200;
+pub const CONFETTI_COLORS: [usize; 6] = [4, 9, 10, 11, 13, 14];
+
+// in pixels and pixels per second
+pub const CAMERA_SHAKE_INTENSITY_PER_CHAIN_DEPTH: f32 = 2.0;
+pub const MAX_CAMERA_SHAKE_INTENSITY: f32 = 12.0;
+pub const CAMERA_SHAKE_DECAY: f32 = 30.0;
+
+pub const GHOST_DISPLAY_FRAMES: u32 = 8;
+
+pub const SLOW_MOTION_DURATION_SECS: f32 = 1.0;
+pub const SLOW_MOTION_RELATIVE_SPEED: f32 = 0.2;
+
+pub const INPUT_UP: u16 = 1 << 0;
+pub const INPUT_DOWN: u16 = 1 << 1;
+pub const INPUT_LEFT: u16 = 1 << 2;
+pub const INPUT_RIGHT: u16 = 1 << 3;
+pub const INPUT_ACTION: u16 = 1 << 4;
+pub const INPUT_DETONATE: u16 = 1 << 5;
+pub const INPUT_EMOTE_1: u16 = 1 << 6;
+pub const INPUT_EMOTE_2: u16 = 1 << 7;
+// set on every frame the action key is down, unlike the other inputs which are only sent when first pressed
+pub const INPUT_ACTION_HELD: u16 = 1 << 8;
 
-pub const ROUND_DURATION_SECS: u32 = 60;
+// the two single emotes, then the combos of pressing both in either order within the combo window
+pub const EMOTE_SYMBOLS: [&str; 4] = ["!", "?", "♪", "♥"];
+pub const EMOTE_DURATION_FRAMES: u32 = 2 * FPS;
+pub const EMOTE_COMBO_WINDOW_FRAMES: u32 = FPS / 3;
 
 pub const FPS: u32 = 30;
 pub const MAX_PREDICTED_FRAMES: u32 = 8;
 
 // these must not be lower than MAX_PREDICTED_FRAMES
-// TODO can some static asserts be made?
 pub const GET_READY_DISPLAY_FRAME_COUNT: u32 = 3 * FPS;
 pub const GAME_START_FREEZE_FRAME_COUNT: u32 = FPS / 2;
 pub const LEADERBOARD_DISPLAY_FRAME_COUNT: u32 = 2 * FPS;
 pub const TOURNAMENT_WINNER_DISPLAY_FRAME_COUNT: u32 = 5 * FPS;
+pub const OVERTIME_MESSAGE_FRAME_COUNT: u32 = FPS;
+const _: () = assert!(GET_READY_DISPLAY_FRAME_COUNT >= MAX_PREDICTED_FRAMES);
+const _: () = assert!(GAME_START_FREEZE_FRAME_COUNT >= MAX_PREDICTED_FRAMES);
+const _: () = assert!(LEADERBOARD_DISPLAY_FRAME_COUNT >= MAX_PREDICTED_FRAMES);
+const _: () = assert!(TOURNAMENT_WINNER_DISPLAY_FRAME_COUNT >= MAX_PREDICTED_FRAMES);
+const _: () = assert!(OVERTIME_MESSAGE_FRAME_COUNT >= MAX_PREDICTED_FRAMES);
 
+pub const RESPAWN_INVULN_FRAMES: u32 = FPS * 2;
+pub const DEATH_BLINK_INTERVAL: u32 = FPS / 10;
+pub const VICTORY_FRAME_INTERVAL: u32 = FPS / 6;
+
+// deliberately lower than MAX_PREDICTED_FRAMES: the shortened expiration frame is only ever computed inside the
+// rollback schedule from rollback registered state, so a mispredicted frame gets resimulated with the corrected
+// expiration like any other game state change and peers can't disagree on it once inputs are confirmed
 pub const BOMB_SHORTENED_FUSE_FRAME_COUNT: u32 = 2;
+// frames before the explosion during which the bomb flashes
+pub const BOMB_FLASH_FRAMES: u32 = FPS;
+pub const BOMB_FLASH_INTERVAL: u32 = FPS / 8;
+// blast areas of bombs this close to exploding get highlighted
+pub const BOMB_WARNING_FRAMES: u32 = FPS;
+pub const MAX_BOMB_WARNING_BOMBS: usize = 3;
+pub const MAX_CHAIN_DEPTH: u8 = 10;
+// above this many fires no new ones are spawned
+pub const MAX_FIRE_ENTITIES: usize = 150;
+
+// how many frames a bomb drop on an occupied tile is retried for
+pub const PENDING_BOMB_WINDOW: u32 = 5;
+// how long the action input has to be held to confirm a bomb drop in hold-to-bomb mode
+pub const BOMB_HOLD_FRAMES: u32 = 5;
 
 pub const MOVING_OBJECT_FRAME_INTERVAL: u32 = 1;
+// frames between two player moves, every speed up lowers it by one
+pub const PLAYER_MOVE_FRAME_INTERVAL: u32 = 3;
+pub const MIN_PLAYER_MOVE_FRAME_INTERVAL: u32 = 1;
+// the player move interval is divided by this during a speed round
+pub const SPEED_ROUND_MOVE_INTERVAL_DIVISOR: u32 = 2;
+const _: () = assert!(
+    PLAYER_MOVE_FRAME_INTERVAL / SPEED_ROUND_MOVE_INTERVAL_DIVISOR < PLAYER_MOVE_FRAME_INTERVAL
+);
+
+// part of the free inner positions that get covered by water
+pub const WATER_TILE_DENSITY: f32 = 0.1;
+pub const WATER_Z_LAYER: f32 = 1.0;
+// part of the free inner positions that get covered by ice which breaks after a few steps
+pub const CRACKABLE_ICE_DENSITY: f32 = 0.15;
+pub const CRACKABLE_ICE_HP: u8 = 3;
+pub const CRACKABLE_ICE_Z_LAYER: f32 = 1.0;
+pub const SLOW_DURATION_FRAMES: u32 = FPS;
+pub const SKULL_STUN_DURATION_FRAMES: u32 = 2 * FPS;
+// every magnet item pulls items from this many more tiles away, up to the maximum
+pub const MAGNET_RADIUS_PER_ITEM: u8 = 2;
+pub const MAX_MAGNET_RADIUS: u8 = 6;
+pub const SLOWED_MOVE_FRAME_DELAY: u32 = 2;
+
+// bump whenever the packet format or the rollback registered state changes in a breaking way
+pub const PROTOCOL_VERSION: u8 = 11;
+
+// lobby packet types sent over the reliable channel
+pub const RNG_SEED_COMMIT_PACKET: u8 = 0x01;
+pub const WORLD_VOTE_PACKET: u8 = 0x07;
+pub const RNG_SEED_REVEAL_PACKET: u8 = 0x08;
+
+pub const SEED_COMMITMENT_LENGTH: usize = 16;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub const TITLE_TIMEOUT_SECS: f32 = 10.0;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub const HIGH_SCORES_PATH: &str = "scores.json";
+#[cfg(not(target_arch = "wasm32"))]
+pub const MAX_HIGH_SCORE_ENTRIES: usize = 10;
+
+// training mode bots only act on every few frames, otherwise they would move a tile on every frame
+#[cfg(not(target_arch = "wasm32"))]
+pub const BOT_MOVE_FRAME_INTERVAL: u32 = FPS / 5;
+
+// Elo rating parameters of the local stats database
+#[cfg(not(target_arch = "wasm32"))]
+pub const INITIAL_RATING: f64 = 1000.0;
+#[cfg(not(target_arch = "wasm32"))]
+pub const RATING_K_FACTOR: f64 = 32.0;
+
+pub const MAX_AUTO_RETRIES: u8 = 3;
+pub const INITIAL_AUTO_RETRY_BACKOFF_SECS: u64 = 2;
+
+// more than this could flood the map with items
+pub const MAX_PINATA_COUNT: u8 = 8;
+// upper limits for the starting bomb satchel
+pub const MAX_BOMB_CAPACITY: u8 = 10;
+pub const MAX_BOMB_RANGE: u8 = 10;
+// upper limit of power-ups returned to the map from a dead player's satchel
+pub const SATCHEL_DROP_MAX_ITEM_COUNT: usize = 5;
 
 // TODO figure out if floats can be used deterministically
 pub const ITEM_SPAWN_CHANCE_PERCENTAGE: u64 = 33;
+
+pub const GAMEPLAY_TIPS: &[&str] = &[
+    "Bombs you place return to your satchel when they explode!",
+    "Push bombs into fire to chain explosions!",
+    "Fire spreads through items, burning them away.",
+    "Explosions shorten the fuse of nearby bombs.",
+    "Destructible walls may hide power-ups.",
+    "Defeated players drop a handful of random items.",
+    "Keep an eye on the clock - the wall of death is coming!",
+    "Bombs block fire, use them as shields.",
+    "Collect the boot to push bombs around.",
+    "A bigger range is not always better in tight corridors.",
+    "Don't trap yourself with your own bombs!",
+    "Corners are safe until they aren't.",
+];
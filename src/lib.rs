@@ -1,3 +1,5 @@
+#[cfg(not(target_arch = "wasm32"))]
+mod bot;
 mod components;
 mod constants;
 #[cfg(not(target_arch = "wasm32"))]
@@ -9,7 +11,7 @@ mod utils;
 #[cfg(target_arch = "wasm32")]
 mod web;
 
-use bevy::{ecs as bevy_ecs, prelude::*};
+use bevy::{ecs as bevy_ecs, prelude::*, transform::TransformSystem};
 use bevy_ggrs::prelude::*;
 
 use types::Cooldown;
@@ -18,31 +20,35 @@ use wasm_bindgen::prelude::*;
 
 #[cfg(target_arch = "wasm32")]
 use crate::web::*;
-use crate::{components::*, constants::FPS, resources::*, systems::*, types::GgrsConfig};
 #[cfg(not(target_arch = "wasm32"))]
 use crate::{
-    native::{native_input, Args},
+    bot::BotPlugin,
+    native::{
+        buffer_keyboard_inputs, flush_game_events, native_input, record_high_score, record_stats,
+        setup_title, setup_training, teardown_title, title_pulse_system, title_system, Args,
+        HighScores, InputBuffer,
+    },
     resources::MatchboxConfig,
 };
+use crate::{components::*, constants::FPS, resources::*, systems::*, types::GgrsConfig};
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, States)]
 pub enum AppState {
+    LoadingAssets,
     #[cfg(target_arch = "wasm32")]
     WebReadyToStart,
+    #[cfg(not(target_arch = "wasm32"))]
+    Title,
     Lobby,
+    #[cfg(not(target_arch = "wasm32"))]
+    Training,
     InGame,
     Error,
 }
 
 impl Default for AppState {
     fn default() -> Self {
-        cfg_if::cfg_if! {
-            if #[cfg(target_arch = "wasm32")] {
-                Self::WebReadyToStart
-            } else {
-                Self::Lobby
-            }
-        }
+        Self::LoadingAssets
     }
 }
 
@@ -72,30 +78,143 @@ pub fn run() {
     .init_resource::<Fonts>()
     .init_resource::<HUDColors>()
     .init_resource::<GameTextures>()
+    .init_resource::<GameIntegrityViolation>()
+    .init_resource::<ChainBurnDepth>()
+    .init_resource::<WorldConfigs>()
+    .init_resource::<WallOfDeathConsumed>()
+    .init_resource::<GameStats>()
+    .init_resource::<FrameInterpolation>()
+    .init_resource::<RollbackDebugState>()
+    .init_resource::<GhostPositionState>()
+    .init_resource::<Map>()
+    .init_resource::<SessionTimer>()
+    .add_event::<BombExplodedEvent>()
+    .add_event::<WallOfDeathDone>()
+    .add_event::<RoundFinishedEvent>()
     .add_state::<AppState>()
     .insert_resource(NetworkStatsCooldown {
-        cooldown: Cooldown::from_seconds(1.0),
+        cooldown: Cooldown::from_seconds_repeating(1.0),
         print_cooldown: 0,
     })
     .add_systems(Update, print_network_stats_system)
+    .add_systems(Update, fade_in_system)
+    .add_systems(Update, confetti_system.run_if(in_state(AppState::InGame)))
+    .add_systems(
+        Update,
+        (confirm_game_integrity_violations, overtime_message_system)
+            .run_if(in_state(AppState::InGame)),
+    )
+    .add_systems(
+        Update,
+        (
+            slow_motion_system,
+            camera_shake_system,
+            rollback_debug_display,
+            ghost_position_system,
+            ghost_cleanup,
+            bomb_ghost_system,
+        )
+            .run_if(in_state(AppState::InGame)),
+    )
+    // runs after the rollback schedule, `Position` stays the authoritative state
+    .add_systems(
+        PostUpdate,
+        (begin_frame_interpolation, apply_interpolation)
+            .chain()
+            .after(TransformSystem::TransformPropagate)
+            .run_if(in_state(AppState::InGame)),
+    )
+    .add_systems(
+        PostUpdate,
+        (wall_of_death_progress_overlay, bomb_warning_overlay).run_if(in_state(AppState::InGame)),
+    )
+    .add_systems(OnEnter(AppState::LoadingAssets), setup_loading)
+    .add_systems(
+        Update,
+        check_loading.run_if(in_state(AppState::LoadingAssets)),
+    )
+    .add_systems(OnExit(AppState::LoadingAssets), teardown_loading)
     .add_systems(
         OnEnter(AppState::Lobby),
         (setup_lobby, start_matchbox_socket),
     )
     .add_systems(Update, lobby_system.run_if(in_state(AppState::Lobby)))
+    .add_systems(
+        Update,
+        room_url_display_system.run_if(in_state(AppState::Lobby)),
+    )
     .add_systems(OnExit(AppState::Lobby), teardown_lobby)
     .add_systems(OnEnter(AppState::InGame), setup_game)
     .add_systems(
         Update,
         handle_ggrs_events.run_if(in_state(AppState::InGame)),
-    );
+    )
+    .add_systems(
+        OnEnter(AppState::Error),
+        (setup_error_screen, setup_auto_retry),
+    )
+    .add_systems(
+        Update,
+        (error_screen_system, auto_retry_system).run_if(in_state(AppState::Error)),
+    )
+    .add_systems(OnExit(AppState::Error), teardown_error_screen);
+
+    #[cfg(not(target_arch = "wasm32"))]
+    app.insert_resource(args.game_config())
+        .insert_resource(args.loot_table())
+        .insert_resource(args.settings());
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(game_event_logger) = args.game_event_logger() {
+        app.insert_resource(game_event_logger)
+            .init_resource::<GameEventQueue>()
+            .add_systems(Update, flush_game_events.run_if(in_state(AppState::InGame)));
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    app.insert_resource(HighScores::load())
+        .add_systems(Update, record_high_score.run_if(in_state(AppState::InGame)));
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(stats_db) = args.stats_db() {
+        app.insert_resource(stats_db)
+            .add_systems(Update, record_stats.run_if(in_state(AppState::InGame)));
+    }
+    #[cfg(target_arch = "wasm32")]
+    app.init_resource::<GameConfig>()
+        .init_resource::<LootTable>()
+        .init_resource::<SettingsResource>();
+
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(training_config) = args.training_config() {
+        app.insert_resource(training_config)
+            .add_plugins(BotPlugin {
+                difficulty: args.bot_difficulty,
+            })
+            .insert_resource(PostLoadingState(AppState::Training))
+            .add_systems(OnEnter(AppState::Training), setup_training);
+    } else {
+        app.insert_resource(PostLoadingState(AppState::Title))
+            .add_systems(OnEnter(AppState::Title), setup_title)
+            .add_systems(
+                Update,
+                (title_system, title_pulse_system).run_if(in_state(AppState::Title)),
+            )
+            .add_systems(OnExit(AppState::Title), teardown_title);
+    }
+    #[cfg(target_arch = "wasm32")]
+    app.insert_resource(PostLoadingState(AppState::WebReadyToStart));
 
     #[cfg(not(target_arch = "wasm32"))]
     app.insert_resource(MatchboxConfig {
+        number_of_players: args.number_of_players(),
         matchbox_server_url: args.matchbox_server_url,
         room_id: args.room_id,
-        number_of_players: args.number_of_players,
         ice_server_config: None,
+        desync_detection_interval: args.desync_detection_interval(),
+        player_input_delays: args.player_input_delays,
+        #[cfg(debug_assertions)]
+        simulated_packet_loss: args.simulate_packet_loss,
+        preferred_world_type: args.world_type,
+        voted_world_type: None,
+        team_assignments: (!args.teams.is_empty()).then_some(args.teams),
     });
 
     #[cfg(target_arch = "wasm32")]
@@ -103,12 +222,18 @@ pub fn run() {
         .add_systems(
             Update,
             web_ready_to_start_update.run_if(in_state(AppState::WebReadyToStart)),
-        );
+        )
+        .add_systems(Update, copy_room_url.run_if(in_state(AppState::Lobby)));
 
     #[cfg(target_arch = "wasm32")]
     let input_fn = web_input;
     #[cfg(not(target_arch = "wasm32"))]
     let input_fn = native_input;
+    #[cfg(not(target_arch = "wasm32"))]
+    app.init_resource::<InputBuffer>().add_systems(
+        First,
+        buffer_keyboard_inputs.run_if(in_state(AppState::InGame)),
+    );
 
     app.add_plugins(GgrsPlugin::<GgrsConfig>::default())
         .set_rollback_schedule_fps(FPS as usize)
@@ -124,8 +249,14 @@ pub fn run() {
         // game components
         .rollback_component_with_copy::<Player>()
         .rollback_component_with_copy::<Dead>()
+        .rollback_component_with_copy::<PlayerHP>()
+        .rollback_component_with_copy::<InvulnFrames>()
+        .rollback_component_with_copy::<DeathAnimation>()
+        .rollback_component_with_copy::<VictoryDance>()
         .rollback_component_with_copy::<Position>()
         .rollback_component_with_copy::<Bomb>()
+        .rollback_component_with_copy::<ChainDepth>()
+        .rollback_component_with_copy::<ChainInitiator>()
         .rollback_component_with_copy::<Moving>()
         .rollback_component_with_copy::<Fuse>()
         .rollback_component_with_copy::<Fire>()
@@ -134,22 +265,47 @@ pub fn run() {
         .rollback_component_with_copy::<Destructible>()
         .rollback_component_with_copy::<Crumbling>()
         .rollback_component_with_copy::<BombSatchel>()
+        .rollback_component_with_copy::<PendingBombDrop>()
         .rollback_component_with_copy::<Item>()
         .rollback_component_with_copy::<BurningItem>()
+        .rollback_component_with_copy::<Emote>()
+        .rollback_component_with_copy::<HeldAction>()
+        .rollback_component_with_copy::<Slowed>()
+        .rollback_component_with_copy::<CrackableIce>()
+        .rollback_component_with_copy::<BombCountOverlay>()
         // resources
         .rollback_resource_with_clone::<SessionRng>()
         .rollback_resource_with_copy::<FrameCount>()
         .rollback_resource_with_copy::<WallOfDeath>()
         .rollback_resource_with_copy::<GameFreeze>()
-        // checksums
+        .rollback_resource_with_copy::<GameConfig>()
+        .rollback_resource_with_clone::<LootTable>()
+        .rollback_resource_with_clone::<WorldConfigs>()
+        .rollback_resource_with_copy::<GameEndFrame>()
+        .rollback_resource_with_copy::<OvertimeActive>()
+        .rollback_resource_with_copy::<CurrentRoundModifier>()
+        .rollback_resource_with_copy::<SessionTimer>()
+        .rollback_resource_with_clone::<GameStats>()
+        .rollback_resource_with_copy::<SessionTimeUp>()
+        // checksums, the derived `Hash` implementations write enum discriminants and lengths as an isize or
+        // usize which isn't portable between platforms, so the hashed types implement it by hand
         .checksum_component_with_hash::<Player>()
         .checksum_component_with_hash::<Position>()
         .checksum_component_with_hash::<BombSatchel>()
-        // enums seem to hash from an isize so the derived implementation isn't portable
+        .checksum_component_with_hash::<Bomb>()
+        .checksum_component_with_hash::<Fire>()
+        .checksum_component_with_hash::<PlayerHP>()
         .checksum_component::<Item>(|item| match item {
             Item::BombsUp => 0,
             Item::RangeUp => 1,
             Item::BombPush => 2,
+            Item::SpeedUp => 3,
+            Item::BombDown => 4,
+            Item::RangeDown => 5,
+            Item::Skull => 6,
+            Item::RemoteBomb => 7,
+            Item::Pierce => 8,
+            Item::Magnet => 9,
         })
         .add_systems(
             GgrsSchedule,
@@ -158,25 +314,40 @@ pub fn run() {
             (
                 (
                     increase_frame_system,
+                    clear_stale_game_events,
                     show_leaderboard,
                     apply_deferred,
                     show_tournament_winner,
                     apply_deferred,
+                    start_new_tournament,
+                    apply_deferred,
                     start_new_round,
                     apply_deferred,
                     finish_actionless_game_freeze,
                     apply_deferred,
                     update_hud_clock,
+                    update_wall_count,
                     update_player_portraits,
+                    update_player_health_bars,
                     apply_deferred,
+                )
+                    .chain(),
+                (
+                    sync_map_system,
                     player_move,
                     apply_deferred,
+                    item_magnetize,
+                    apply_deferred,
                     bomb_move,
                     apply_deferred,
                     pick_up_item,
                     apply_deferred,
                     bomb_drop,
                     apply_deferred,
+                    remote_detonate_system,
+                    emote_system,
+                    apply_deferred,
+                    update_bomb_count_overlay,
                 )
                     .chain(),
                 (
@@ -184,13 +355,18 @@ pub fn run() {
                     apply_deferred,
                     crumbling_tick,
                     apply_deferred,
+                    crackable_ice_tick,
+                    apply_deferred,
                     burning_item_tick,
                     apply_deferred,
                     explode_bombs,
                     apply_deferred,
+                    clear_bomb_tint,
+                    flash_near_expiry_bombs,
                     animate_fuse,
                     player_burn,
                     apply_deferred,
+                    animate_death,
                 )
                     .chain(),
                 (
@@ -198,12 +374,17 @@ pub fn run() {
                     apply_deferred,
                     item_burn,
                     apply_deferred,
+                    chain_burn,
+                    apply_deferred,
                     wall_of_death_update,
+                    log_wall_of_death_done,
                     apply_deferred,
                     cleanup_dead,
                     apply_deferred,
                     check_game_rules,
                     finish_round,
+                    animate_victory_dance,
+                    record_ghost_positions,
                 )
                     .chain(),
             )
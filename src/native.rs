@@ -1,14 +1,46 @@
-use std::ffi::OsString;
+use std::{
+    ffi::OsString,
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
 
-use bevy::{ecs as bevy_ecs, prelude::*, utils::HashMap};
-use bevy_ggrs::{LocalInputs, LocalPlayers};
-use clap::Parser;
-use serde::Deserialize;
+use bevy::{
+    ecs as bevy_ecs,
+    input::{keyboard::KeyboardInput, ButtonState},
+    prelude::*,
+    utils::{HashMap, HashSet},
+    window::PrimaryWindow,
+};
+use bevy_ggrs::{
+    ggrs::{PlayerType, SessionBuilder},
+    LocalInputs, LocalPlayers, Session,
+};
+use clap::{CommandFactory, Parser};
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    constants::{INPUT_ACTION, INPUT_DOWN, INPUT_LEFT, INPUT_RIGHT, INPUT_UP},
-    resources::GameFreeze,
-    types::{GgrsConfig, PlayerInput},
+    bot::BotDifficulty,
+    components::{Dead, Player, Position},
+    constants::{
+        BOMB_SHORTENED_FUSE_FRAME_COUNT, COLORS, FPS, HIGH_SCORES_PATH, HUD_HEIGHT, INITIAL_RATING,
+        INPUT_ACTION, INPUT_ACTION_HELD, INPUT_DETONATE, INPUT_DOWN, INPUT_EMOTE_1, INPUT_EMOTE_2,
+        INPUT_LEFT, INPUT_RIGHT, INPUT_UP, MAX_BOMB_CAPACITY, MAX_BOMB_RANGE,
+        MAX_HIGH_SCORE_ENTRIES, MAX_PINATA_COUNT, MAX_PREDICTED_FRAMES, PIXEL_SCALE,
+        RATING_K_FACTOR, TILE_HEIGHT, TILE_WIDTH, TITLE_TIMEOUT_SECS,
+    },
+    resources::{
+        CurrentError, Fonts, GameConfig, GameEvent, GameEventQueue, GameFreeze, GameStats,
+        Leaderboard, LocalPlayerID, LootTable, MapSize, MatchboxConfig, RoundFinishedEvent,
+        SessionRng, SettingsResource, TrainingConfig, WorldType,
+    },
+    types::{
+        GgrsConfig, MapLayout, MapSymmetry, PlayerID, PlayerInput, RoundOutcome, ScoreMode,
+        TournamentFormat,
+    },
+    utils::setup_map_view,
+    AppState,
 };
 
 #[derive(Parser, Debug, Clone, Deserialize, Resource)]
@@ -27,6 +59,162 @@ pub struct Args {
 
     #[clap(long, short, default_value = "2")]
     pub number_of_players: u8,
+
+    /// Play offline against bots, nothing is sent over the network
+    #[clap(long)]
+    pub training: bool,
+
+    /// Number of bot opponents in training mode (1 - 7)
+    #[clap(long, default_value = "1")]
+    pub training_bots: u8,
+
+    #[clap(long, default_value = "60")]
+    pub round_duration_secs: u32,
+
+    /// Ends the running round and tournament at this time, the session is over afterwards
+    #[clap(long)]
+    pub max_session_duration_secs: Option<u32>,
+
+    /// Number of round wins needed to win the tournament
+    #[clap(long, default_value = "3")]
+    pub winning_score: u8,
+
+    /// Tournament format: first-to-N | best-of-N (odd N), overrides the winning score
+    #[clap(long)]
+    #[serde(skip)]
+    pub tournament_format: Option<TournamentFormat>,
+
+    /// Carry round wins over between tournaments
+    #[clap(long)]
+    pub cumulative_score: bool,
+
+    /// Number of bombs every player starts with (1 - 10)
+    #[clap(
+        long,
+        default_value = "1",
+        value_parser = clap::value_parser!(u8).range(1..=MAX_BOMB_CAPACITY as i64)
+    )]
+    pub initial_bombs: u8,
+
+    /// Bomb range every player starts with (1 - 10)
+    #[clap(
+        long,
+        default_value = "2",
+        value_parser = clap::value_parser!(u8).range(1..=MAX_BOMB_RANGE as i64)
+    )]
+    pub initial_range: u8,
+
+    /// Number of fire hits a player survives, one-hit kills if not set
+    #[clap(long)]
+    pub player_hp: Option<u8>,
+
+    /// Number of frames until a dropped bomb explodes, longer than twice the shortened fuse of chained bombs
+    #[clap(
+        long,
+        default_value_t = 2 * FPS,
+        value_parser = clap::value_parser!(u32).range((BOMB_SHORTENED_FUSE_FRAME_COUNT * 2 + 1) as i64..)
+    )]
+    pub bomb_fuse_frames: u32,
+
+    #[clap(long, default_value = "3")]
+    pub pinata_count: u8,
+
+    /// Shorthand for `--pinata-count 0`
+    #[clap(long)]
+    pub no_pinata: bool,
+
+    /// Extend rounds that time out with multiple survivors instead of calling a tie
+    #[clap(long)]
+    pub overtime: bool,
+
+    /// Drop a bomb once the action key has been held for a few frames or on releasing a quick tap, previewing it while held
+    #[clap(long)]
+    pub hold_to_bomb: bool,
+
+    /// Drop bombs by left-clicking on the local player
+    #[clap(long)]
+    pub mouse_input: bool,
+
+    /// Gamepad analog stick deflection (0 - 1) below which the stick is ignored
+    #[clap(long, default_value = "0.3")]
+    pub gamepad_deadzone: f32,
+
+    /// Let a bot control the local player in training mode
+    #[clap(long, requires = "training")]
+    pub bot_player: bool,
+
+    /// Difficulty of the training mode bots: easy | medium | hard
+    #[clap(long, default_value = "medium")]
+    #[serde(skip)]
+    pub bot_difficulty: BotDifficulty,
+
+    /// Map layout: random | maze
+    #[clap(long, default_value = "random")]
+    #[serde(skip)]
+    pub map_layout: MapLayout,
+
+    /// Destructible wall symmetry of random maps: none | 180
+    #[clap(long, default_value = "none")]
+    #[serde(skip)]
+    pub map_symmetry: MapSymmetry,
+
+    /// Fraction of the passable positions covered by destructible walls (0.1 - 0.9)
+    #[clap(long, default_value = "0.4")]
+    pub wall_density: f32,
+
+    /// Path to a TOML file with a custom loot table
+    #[clap(long)]
+    pub loot_table: Option<PathBuf>,
+    // read from the `loot_table` file when the arguments are parsed
+    #[clap(skip)]
+    #[serde(skip)]
+    pub custom_loot_table: Option<LootTable>,
+
+    /// Number of frames between desync checks (0 disables them), at most the prediction window.
+    /// Lower values catch desyncs sooner at the cost of extra checksum traffic.
+    #[clap(
+        long = "desync-interval",
+        default_value = "1",
+        value_parser = clap::value_parser!(u32).range(0..=MAX_PREDICTED_FRAMES as i64)
+    )]
+    pub desync_interval: u32,
+
+    /// Input delay in frames for each player handle, e.g. `0,2,2,4`.
+    /// GGRS only delays local inputs, so only the entry of the local player takes effect.
+    #[clap(long, value_delimiter = ',')]
+    pub player_input_delays: Vec<usize>,
+
+    /// Team of each player handle, e.g. `0,0,1,1`, free-for-all if not set.
+    /// Teammates can't hurt each other and share their round wins.
+    #[clap(long, value_delimiter = ',')]
+    pub teams: Vec<u8>,
+
+    /// Percentage of outgoing game packets to drop (0 - 100), only available in debug builds
+    #[cfg(debug_assertions)]
+    #[clap(long, default_value = "0", value_parser = clap::value_parser!(u8).range(0..=100))]
+    pub simulate_packet_loss: u8,
+
+    /// Overrides the map size chosen based on the player count, e.g. `11x15`
+    #[clap(long)]
+    #[serde(skip)]
+    pub force_map_size: Option<MapSize>,
+
+    /// Vote for the starting world: grass | ice | cloud
+    #[clap(long)]
+    #[serde(skip)]
+    pub world_type: Option<WorldType>,
+
+    /// Write game events as JSON lines to this file
+    #[clap(long)]
+    pub log_file: Option<PathBuf>,
+
+    /// Verbosity of the game event log: 1 deaths, 2 bombs/items/walls, 3 movement
+    #[clap(long, default_value = "2")]
+    pub log_level: u8,
+
+    /// Directory of the local stats database, which keeps per-round stats and player ratings
+    #[clap(long)]
+    pub stats_db: Option<PathBuf>,
 }
 
 impl Default for Args {
@@ -38,51 +226,857 @@ impl Default for Args {
 
 impl Args {
     pub fn get() -> Self {
-        Args::parse()
+        let mut args = Args::parse();
+        if !args.teams.is_empty() && args.teams.len() != args.number_of_players() as usize {
+            Args::command()
+                .error(
+                    clap::error::ErrorKind::ValueValidation,
+                    format!(
+                        "--teams needs a team for each of the {} players",
+                        args.number_of_players()
+                    ),
+                )
+                .exit();
+        }
+        if let Some(path) = &args.loot_table {
+            match read_loot_table(path) {
+                Ok(loot_table) => args.custom_loot_table = Some(loot_table),
+                Err(e) => Args::command()
+                    .error(
+                        clap::error::ErrorKind::ValueValidation,
+                        format!("--loot-table {}: {e}", path.display()),
+                    )
+                    .exit(),
+            }
+        }
+        args
+    }
+
+    pub fn training_config(&self) -> Option<TrainingConfig> {
+        self.training.then(|| TrainingConfig {
+            bots: self.training_bots.clamp(1, 7),
+            bot_player: self.bot_player,
+        })
+    }
+
+    // bots fill the remaining slots in training mode
+    pub fn number_of_players(&self) -> u8 {
+        match self.training_config() {
+            Some(training_config) => 1 + training_config.bots,
+            None => self.number_of_players,
+        }
+    }
+
+    pub fn game_config(&self) -> GameConfig {
+        let pinata_count = if self.no_pinata {
+            0
+        } else {
+            self.pinata_count.min(MAX_PINATA_COUNT)
+        };
+
+        GameConfig {
+            round_duration_secs: self.round_duration_secs.max(1),
+            tournament_format: self
+                .tournament_format
+                .unwrap_or(TournamentFormat::FirstToN(self.winning_score.max(1))),
+            score_mode: if self.cumulative_score {
+                ScoreMode::Cumulative
+            } else {
+                ScoreMode::ResetBetweenTournaments
+            },
+            initial_bombs: self.initial_bombs,
+            initial_range: self.initial_range,
+            bomb_fuse_frames: self.bomb_fuse_frames,
+            pinata_count,
+            overtime_enabled: self.overtime,
+            map_layout: self.map_layout,
+            map_symmetry: self.map_symmetry,
+            wall_density_permille: (self.wall_density.clamp(0.1, 0.9) * 1000.0).round() as u32,
+            forced_map_size: self.force_map_size,
+            player_hp: self.player_hp.map(|hp| hp.max(1)),
+            hold_to_bomb: self.hold_to_bomb,
+            max_session_frames: self.max_session_duration_secs.map(|secs| secs * FPS),
+            ..Default::default()
+        }
+    }
+
+    pub fn desync_detection_interval(&self) -> Option<u32> {
+        (self.desync_interval > 0).then_some(self.desync_interval)
+    }
+
+    pub fn loot_table(&self) -> LootTable {
+        self.custom_loot_table.clone().unwrap_or_default()
+    }
+
+    pub fn game_event_logger(&self) -> Option<GameEventLogger> {
+        let path = self.log_file.as_ref()?;
+        let file = match File::create(path) {
+            Ok(file) => file,
+            Err(e) => {
+                error!("Failed to create the log file {path:?}, game events won't be logged: {e}");
+                return None;
+            }
+        };
+        Some(GameEventLogger {
+            writer: BufWriter::new(file),
+            level: self.log_level,
+            last_flushed_frame: 0,
+        })
+    }
+
+    pub fn stats_db(&self) -> Option<StatsDb> {
+        let path = self.stats_db.as_ref()?;
+        let db = match sled::open(path) {
+            Ok(db) => db,
+            Err(e) => {
+                error!("Failed to open the stats database at {path:?}: {e}");
+                return None;
+            }
+        };
+
+        let started_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let session_id = format!("{started_at}-{}", self.room_id);
+        let session = serde_json::json!({
+            "started_at": started_at,
+            "room_id": self.room_id,
+            "number_of_players": self.number_of_players(),
+        });
+        if let Err(e) = db
+            .open_tree("sessions")
+            .and_then(|sessions| sessions.insert(&session_id, session.to_string().as_bytes()))
+        {
+            error!("Failed to record the session in the stats database: {e}");
+        }
+
+        Some(StatsDb {
+            db,
+            session_id,
+            rounds_recorded: 0,
+        })
+    }
+
+    pub fn settings(&self) -> SettingsResource {
+        SettingsResource {
+            mouse_input: self.mouse_input,
+            gamepad_deadzone: self.gamepad_deadzone,
+            ..Default::default()
+        }
     }
 }
 
-pub fn native_input(
+fn read_loot_table(path: &Path) -> Result<LootTable, String> {
+    let contents = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let loot_table: LootTable = toml::from_str(&contents).map_err(|e| e.to_string())?;
+    if loot_table.entries.iter().map(|e| e.1).sum::<u64>() == 0 {
+        return Err("the loot table weights must not all be zero".to_string());
+    }
+    if loot_table.spawn_chance > 100 {
+        return Err("the loot table spawn chance is a percentage".to_string());
+    }
+    Ok(loot_table)
+}
+
+#[derive(Resource)]
+pub struct GameEventLogger {
+    writer: BufWriter<File>,
+    level: u8,
+    last_flushed_frame: u32,
+}
+
+// events are only written once their frame is confirmed, so rollbacks don't show up in the log
+pub fn flush_game_events(
+    mut game_event_logger: ResMut<GameEventLogger>,
+    mut game_event_queue: ResMut<GameEventQueue>,
+    session: Option<Res<Session<GgrsConfig>>>,
+) {
+    let Some(Session::P2P(s)) = session.as_deref() else {
+        return;
+    };
+    let Ok(confirmed_frame) = u32::try_from(s.confirmed_frame()) else {
+        return;
+    };
+
+    if confirmed_frame < game_event_logger.last_flushed_frame {
+        // a new session was started
+        game_event_logger.last_flushed_frame = 0;
+    }
+
+    let last_flushed_frame = game_event_logger.last_flushed_frame;
+    let (confirmed_events, pending_events): (Vec<GameEvent>, Vec<GameEvent>) = game_event_queue
+        .0
+        .drain(..)
+        .partition(|event| event.frame <= confirmed_frame);
+    game_event_queue.0 = pending_events;
+
+    let level = game_event_logger.level;
+    let mut written = false;
+    for event in confirmed_events
+        .iter()
+        .filter(|event| event.frame > last_flushed_frame && event.kind.level() <= level)
+    {
+        let writer = &mut game_event_logger.writer;
+        if let Err(e) = serde_json::to_writer(&mut *writer, event)
+            .map_err(std::io::Error::from)
+            .and_then(|_| writeln!(writer))
+        {
+            error!("Failed to write a game event: {e}");
+            return;
+        }
+        written = true;
+    }
+    if written {
+        if let Err(e) = game_event_logger.writer.flush() {
+            error!("Failed to flush the game event log: {e}");
+        }
+    }
+
+    game_event_logger.last_flushed_frame = confirmed_frame;
+}
+
+// writes happen on background threads, sled handles are cheap to clone and thread-safe
+#[derive(Resource)]
+pub struct StatsDb {
+    db: sled::Db,
+    session_id: String,
+    rounds_recorded: u32,
+}
+
+// there are no persistent player identities yet, so players are named after their handles
+fn stats_player_name(player_id: PlayerID) -> String {
+    format!("player_{}", player_id.0)
+}
+
+pub fn record_stats(
+    mut stats_db: ResMut<StatsDb>,
+    mut round_finished_events: EventReader<RoundFinishedEvent>,
+    leaderboard: Res<Leaderboard>,
+    game_stats: Res<GameStats>,
+    mut last_recorded_frame: Local<Option<u32>>,
+) {
+    for event in round_finished_events.read() {
+        // resimulated rounds are only recorded once
+        if *last_recorded_frame == Some(event.frame) {
+            continue;
+        }
+        *last_recorded_frame = Some(event.frame);
+
+        stats_db.rounds_recorded += 1;
+        let session_id = stats_db.session_id.clone();
+
+        let round_key = format!("{session_id}/{:04}", stats_db.rounds_recorded);
+        let round = serde_json::json!({
+            "winner": match event.round_outcome {
+                RoundOutcome::Winner(player_id) => Some(stats_player_name(player_id)),
+                RoundOutcome::TeamWinner(team) => Some(format!("team_{team}")),
+                RoundOutcome::Tie => None,
+            },
+        });
+
+        let player_stats = leaderboard
+            .scores
+            .keys()
+            .map(|&player_id| {
+                let score = leaderboard.total_score(player_id);
+                let stat =
+                    |stats: &HashMap<PlayerID, u32>| stats.get(&player_id).copied().unwrap_or(0);
+                let stats = serde_json::json!({
+                    "score": score,
+                    "longest_chain": stat(&game_stats.longest_chain),
+                    "items_collected": stat(&game_stats.items_collected),
+                    "tiles_walked": stat(&game_stats.tiles_walked),
+                });
+                (
+                    format!("{session_id}/{}", stats_player_name(player_id)),
+                    stats.to_string(),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let rating_update = event.tournament_winner.map(|winner| {
+            let losers = leaderboard
+                .scores
+                .keys()
+                .filter(|&&player_id| !leaderboard.is_same_side(player_id, winner))
+                .map(|&player_id| stats_player_name(player_id))
+                .collect::<Vec<_>>();
+            (stats_player_name(winner), losers)
+        });
+
+        let db = stats_db.db.clone();
+        std::thread::spawn(move || {
+            if let Err(e) = write_round_stats(&db, round_key, round, player_stats, rating_update) {
+                error!("Failed to write to the stats database: {e}");
+            }
+        });
+    }
+}
+
+fn write_round_stats(
+    db: &sled::Db,
+    round_key: String,
+    round: serde_json::Value,
+    player_stats: Vec<(String, String)>,
+    rating_update: Option<(String, Vec<String>)>,
+) -> sled::Result<()> {
+    db.open_tree("rounds")?
+        .insert(round_key, round.to_string().as_bytes())?;
+
+    let player_stats_tree = db.open_tree("player_stats")?;
+    for (key, stats) in player_stats {
+        player_stats_tree.insert(key, stats.as_bytes())?;
+    }
+
+    if let Some((winner, losers)) = rating_update {
+        update_ratings(&db.open_tree("ratings")?, &winner, &losers)?;
+    }
+
+    db.flush()?;
+    Ok(())
+}
+
+// the tournament winner counts as having beaten every other player
+fn update_ratings(ratings: &sled::Tree, winner: &str, losers: &[String]) -> sled::Result<()> {
+    let get_rating = |name: &str| -> sled::Result<f64> {
+        Ok(ratings
+            .get(name)?
+            .and_then(|bytes| <[u8; 8]>::try_from(&bytes[..]).ok())
+            .map(f64::from_be_bytes)
+            .unwrap_or(INITIAL_RATING))
+    };
+
+    let mut winner_rating = get_rating(winner)?;
+    for loser in losers {
+        let loser_rating = get_rating(loser)?;
+        let expected_win = 1.0 / (1.0 + 10f64.powf((loser_rating - winner_rating) / 400.0));
+        let adjustment = RATING_K_FACTOR * (1.0 - expected_win);
+
+        winner_rating += adjustment;
+        ratings.insert(
+            loser.as_str(),
+            (loser_rating - adjustment).to_be_bytes().to_vec(),
+        )?;
+    }
+    ratings.insert(winner, winner_rating.to_be_bytes().to_vec())?;
+
+    Ok(())
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct HighScoreEntry {
+    pub name: String,
+    pub wins: u32,
+    // seconds since the Unix epoch
+    pub timestamp: i64,
+}
+
+// not part of the game state, persisted across sessions
+#[derive(Resource, Default, Serialize, Deserialize)]
+pub struct HighScores {
+    pub entries: Vec<HighScoreEntry>,
+}
+
+impl HighScores {
+    pub fn load() -> Self {
+        match fs::read_to_string(HIGH_SCORES_PATH) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+                error!("Failed to parse the high scores file: {e}");
+                Self::default()
+            }),
+            // no tournament has been won yet
+            Err(_) => Self::default(),
+        }
+    }
+
+    fn save(&self) {
+        let result = serde_json::to_string_pretty(self)
+            .map_err(|e| e.to_string())
+            .and_then(|contents| fs::write(HIGH_SCORES_PATH, contents).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            error!("Failed to write the high scores file: {e}");
+        }
+    }
+
+    fn qualifies(&self, wins: u32) -> bool {
+        self.entries.len() < MAX_HIGH_SCORE_ENTRIES
+            || self.entries.last().is_some_and(|lowest| wins > lowest.wins)
+    }
+}
+
+pub fn record_high_score(
+    mut high_scores: ResMut<HighScores>,
+    mut round_finished_events: EventReader<RoundFinishedEvent>,
+    leaderboard: Res<Leaderboard>,
+    mut last_recorded_frame: Local<Option<u32>>,
+) {
+    for event in round_finished_events.read() {
+        // resimulated rounds are only recorded once
+        if *last_recorded_frame == Some(event.frame) {
+            continue;
+        }
+        *last_recorded_frame = Some(event.frame);
+
+        let Some(winner) = event.tournament_winner else {
+            continue;
+        };
+        let name = stats_player_name(winner);
+        let wins = leaderboard.total_score(winner);
+        if leaderboard.score_mode == ScoreMode::Cumulative {
+            // the career total replaces the player's previous entry
+            high_scores.entries.retain(|entry| entry.name != name);
+        }
+        if !high_scores.qualifies(wins) {
+            continue;
+        }
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs() as i64;
+        high_scores.entries.push(HighScoreEntry {
+            name,
+            wins,
+            timestamp,
+        });
+        high_scores.entries.sort_by(|a, b| b.wins.cmp(&a.wins));
+        high_scores.entries.truncate(MAX_HIGH_SCORE_ENTRIES);
+        high_scores.save();
+    }
+}
+
+#[derive(Component)]
+pub struct TitleText;
+
+#[derive(Component)]
+pub struct TitlePrompt;
+
+#[derive(Resource)]
+pub struct TitleTimeout(pub Timer);
+
+pub fn setup_title(
     mut commands: Commands,
+    fonts: Res<Fonts>,
+    high_scores: Res<HighScores>,
+    primary_window_query: Query<&Window, With<PrimaryWindow>>,
+) {
+    commands.spawn(Camera2dBundle::default());
+    commands.insert_resource(TitleTimeout(Timer::from_seconds(
+        TITLE_TIMEOUT_SECS,
+        TimerMode::Once,
+    )));
+
+    let window = primary_window_query.single();
+    let center_y = window.height() / 2.0;
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                row_gap: Val::Px(center_y / 4.0),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "ASCII BOMB ECS",
+                    TextStyle {
+                        font: fonts.mono.clone(),
+                        font_size: 3.0 * PIXEL_SCALE as f32,
+                        color: COLORS[15].into(),
+                    },
+                ),
+                TitleText,
+            ));
+            parent.spawn((
+                TextBundle::from_section(
+                    "Press Enter to start",
+                    TextStyle {
+                        font: fonts.mono.clone(),
+                        font_size: 2.0 * PIXEL_SCALE as f32,
+                        color: COLORS[7].into(),
+                    },
+                ),
+                TitlePrompt,
+            ));
+
+            if !high_scores.entries.is_empty() {
+                let table = high_scores
+                    .entries
+                    .iter()
+                    .enumerate()
+                    .map(|(i, entry)| format!("{:>2}. {:<12}{:>4}", i + 1, entry.name, entry.wins))
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                parent.spawn(
+                    TextBundle::from_sections([
+                        TextSection::new(
+                            "All-Time Champions\n\n",
+                            TextStyle {
+                                font: fonts.mono.clone(),
+                                font_size: 2.0 * PIXEL_SCALE as f32,
+                                color: COLORS[14].into(),
+                            },
+                        ),
+                        TextSection::new(
+                            table,
+                            TextStyle {
+                                font: fonts.mono.clone(),
+                                font_size: 1.5 * PIXEL_SCALE as f32,
+                                color: COLORS[15].into(),
+                            },
+                        ),
+                    ])
+                    .with_text_alignment(TextAlignment::Left),
+                );
+            }
+        });
+}
+
+pub fn title_pulse_system(
+    time: Res<Time>,
+    mut title_text_query: Query<&mut Text, With<TitleText>>,
+    mut title_prompt_query: Query<&mut Visibility, With<TitlePrompt>>,
+) {
+    let t = time.elapsed_seconds();
+    for mut text in title_text_query.iter_mut() {
+        // oscillates between 3.0 and 3.5 pixel scales
+        text.sections[0].style.font_size = (3.25 + 0.25 * t.sin()) * PIXEL_SCALE as f32;
+    }
+
+    // flash the prompt twice a second
+    for mut visibility in title_prompt_query.iter_mut() {
+        *visibility = if (t * 2.0) as u32 % 2 == 0 {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
+
+pub fn title_system(
+    time: Res<Time>,
     keyboard_input: Res<Input<KeyCode>>,
-    local_players: Res<LocalPlayers>,
-    mut last_kb_input: Local<u8>,
-    game_freeze: Option<Res<GameFreeze>>,
+    mut title_timeout: ResMut<TitleTimeout>,
+    mut app_state: ResMut<NextState<AppState>>,
 ) {
-    // there must be only one local player
-    assert_eq!(local_players.0.len(), 1);
-    let local_player_handle = *local_players.0.first().unwrap();
+    title_timeout.0.tick(time.delta());
+    if keyboard_input.just_pressed(KeyCode::Return) || title_timeout.0.finished() {
+        app_state.set(AppState::Lobby);
+    }
+}
+
+pub fn teardown_title(
+    mut commands: Commands,
+    // the lobby spawns a new camera
+    teardown_entities_query: Query<Entity, Without<Window>>,
+) {
+    commands.remove_resource::<TitleTimeout>();
+    teardown_entities_query
+        .iter()
+        .for_each(|e| commands.entity(e).despawn());
+}
 
-    // process keyboard input
-    let mut kb_input: u8 = 0;
+#[derive(Resource, Default)]
+pub struct InputBuffer {
+    pub pending: u16,
+    // tracked per key since several keys map to the same input
+    held_keys: HashSet<KeyCode>,
+}
+
+fn key_code_to_input(key_code: KeyCode) -> Option<u16> {
+    match key_code {
+        KeyCode::Up | KeyCode::W => Some(INPUT_UP),
+        KeyCode::Left | KeyCode::A => Some(INPUT_LEFT),
+        KeyCode::Down | KeyCode::S => Some(INPUT_DOWN),
+        KeyCode::Right | KeyCode::D => Some(INPUT_RIGHT),
+        KeyCode::Space | KeyCode::ControlLeft | KeyCode::ShiftLeft => Some(INPUT_ACTION),
+        KeyCode::R => Some(INPUT_DETONATE),
+        KeyCode::Q => Some(INPUT_EMOTE_1),
+        KeyCode::E => Some(INPUT_EMOTE_2),
+        _ => None,
+    }
+}
+
+pub fn buffer_keyboard_inputs(
+    mut keyboard_input_events: EventReader<KeyboardInput>,
+    mut input_buffer: ResMut<InputBuffer>,
+) {
+    for event in keyboard_input_events.read() {
+        let Some((key_code, input)) = event
+            .key_code
+            .and_then(|key_code| key_code_to_input(key_code).map(|input| (key_code, input)))
+        else {
+            continue;
+        };
 
-    if keyboard_input.pressed(KeyCode::Up) {
-        kb_input |= INPUT_UP;
+        match event.state {
+            ButtonState::Pressed => {
+                // only acknowledge new keyboard input, not held down keys
+                if input_buffer.held_keys.insert(key_code) {
+                    input_buffer.pending |= input;
+                }
+            }
+            ButtonState::Released => {
+                input_buffer.held_keys.remove(&key_code);
+            }
+        }
     }
-    if keyboard_input.pressed(KeyCode::Left) {
-        kb_input |= INPUT_LEFT;
+}
+
+// skips the lobby, all the players are local and every one but the first is a bot
+pub fn setup_training(
+    mut commands: Commands,
+    matchbox_config: Res<MatchboxConfig>,
+    game_config: Res<GameConfig>,
+    mut primary_window_query: Query<&mut Window, With<PrimaryWindow>>,
+    mut app_state: ResMut<NextState<AppState>>,
+) {
+    let map_size = game_config
+        .forced_map_size
+        .unwrap_or_else(|| MapSize::from_player_count(matchbox_config.number_of_players));
+    if let Err(e) = map_size.validate() {
+        error!("Invalid map size {map_size:?}: {e}");
+        commands.insert_resource(CurrentError("INVALID MAP SIZE!".to_string()));
+        app_state.set(AppState::Error);
+        return;
     }
-    if keyboard_input.pressed(KeyCode::Down) {
-        kb_input |= INPUT_DOWN;
+    commands.insert_resource(map_size);
+
+    let mut window = primary_window_query.single_mut();
+    setup_map_view(&mut commands, &mut window, map_size);
+
+    // there are no peers to exchange seeds with
+    commands.insert_resource(SessionRng::from_entropy());
+
+    commands.insert_resource(LocalPlayerID(0));
+    commands.insert_resource(training_session(matchbox_config.number_of_players));
+
+    app_state.set(AppState::InGame);
+}
+
+fn training_session(number_of_players: u8) -> Session<GgrsConfig> {
+    // without a check distance the sync test session never resimulates frames, which the systems that end
+    // a freeze rely on as they change state that isn't rolled back (e.g. the leaderboard)
+    let mut sess_build = SessionBuilder::<GgrsConfig>::new()
+        .with_num_players(number_of_players as usize)
+        .with_input_delay(0)
+        .with_check_distance(0);
+    for i in 0..number_of_players as usize {
+        sess_build = sess_build
+            .add_player(PlayerType::Local, i)
+            .expect("failed to add player");
     }
-    if keyboard_input.pressed(KeyCode::Right) {
-        kb_input |= INPUT_RIGHT;
+
+    let sess = sess_build
+        .start_synctest_session()
+        .expect("failed to start session");
+    Session::SyncTest(sess)
+}
+
+pub fn native_input(
+    mut commands: Commands,
+    mut input_buffer: ResMut<InputBuffer>,
+    mouse_button_input: Res<Input<MouseButton>>,
+    gamepads: Res<Gamepads>,
+    gamepad_button_input: Res<Input<GamepadButton>>,
+    gamepad_axes: Res<Axis<GamepadAxis>>,
+    mut last_gamepad_input: Local<u16>,
+    settings: Res<SettingsResource>,
+    local_players: Res<LocalPlayers>,
+    primary_window_query: Query<&Window, With<PrimaryWindow>>,
+    player_query: Query<(&Player, &Position), Without<Dead>>,
+    game_freeze: Option<Res<GameFreeze>>,
+) {
+    // the first local player is the human one, the rest are training mode bots
+    let local_player_handle = *local_players.0.first().unwrap();
+
+    // process mouse input
+    let mut mouse_input: u16 = 0;
+
+    if settings.mouse_input && mouse_button_input.just_pressed(MouseButton::Left) {
+        if let Some(cursor) = primary_window_query.single().cursor_position() {
+            // the cursor position is relative to the top-left corner of the window, above the map is the HUD
+            if cursor.y >= HUD_HEIGHT as f32 {
+                let cursor_position = Position {
+                    y: ((cursor.y - HUD_HEIGHT as f32) / TILE_HEIGHT as f32) as u8,
+                    x: (cursor.x / TILE_WIDTH as f32) as u8,
+                };
+
+                if player_query.iter().any(|(player, &position)| {
+                    player.id.0 as usize == local_player_handle && position == cursor_position
+                }) {
+                    mouse_input |= INPUT_ACTION;
+                }
+            }
+        }
     }
-    if keyboard_input.pressed(KeyCode::Space) {
-        kb_input |= INPUT_ACTION;
+
+    // process gamepad input, there is a single local human player so only the first gamepad is used
+    let mut gamepad_input: u16 = 0;
+
+    if let Some(gamepad) = gamepads.iter().next() {
+        for (button_type, input) in [
+            (GamepadButtonType::DPadUp, INPUT_UP),
+            (GamepadButtonType::DPadDown, INPUT_DOWN),
+            (GamepadButtonType::DPadLeft, INPUT_LEFT),
+            (GamepadButtonType::DPadRight, INPUT_RIGHT),
+            (GamepadButtonType::South, INPUT_ACTION),
+        ] {
+            if gamepad_button_input.pressed(GamepadButton::new(gamepad, button_type)) {
+                gamepad_input |= input;
+            }
+        }
+
+        let stick_axis = |axis_type| {
+            gamepad_axes
+                .get(GamepadAxis::new(gamepad, axis_type))
+                .unwrap_or(0.0)
+        };
+        let stick_x = stick_axis(GamepadAxisType::LeftStickX);
+        let stick_y = stick_axis(GamepadAxisType::LeftStickY);
+        if stick_y > settings.gamepad_deadzone {
+            gamepad_input |= INPUT_UP;
+        } else if stick_y < -settings.gamepad_deadzone {
+            gamepad_input |= INPUT_DOWN;
+        }
+        if stick_x > settings.gamepad_deadzone {
+            gamepad_input |= INPUT_RIGHT;
+        } else if stick_x < -settings.gamepad_deadzone {
+            gamepad_input |= INPUT_LEFT;
+        }
     }
 
-    // only acknowledge new keyboard input
-    let input = !*last_kb_input & kb_input;
-    *last_kb_input = kb_input;
+    // only acknowledge new gamepad input, like with the keyboard
+    let new_gamepad_input = !*last_gamepad_input & gamepad_input;
+    *last_gamepad_input = gamepad_input;
+
+    // drain the keyboard input buffered since the last frame
+    let mut input = std::mem::take(&mut input_buffer.pending) | mouse_input | new_gamepad_input;
+
+    // the held state is sent on every frame so that hold-to-bomb can tell a hold from a tap
+    if input_buffer
+        .held_keys
+        .iter()
+        .any(|&key_code| key_code_to_input(key_code) == Some(INPUT_ACTION))
+        || gamepad_input & INPUT_ACTION != 0
+    {
+        input |= INPUT_ACTION_HELD;
+    }
 
     let mut local_inputs = HashMap::new();
     if game_freeze.is_some() {
         // override inputs during a freeze as the game must not be rolled back at this time
-        local_inputs.insert(local_player_handle, PlayerInput(0));
+        for &handle in &local_players.0 {
+            local_inputs.insert(handle, PlayerInput(0));
+        }
     } else {
         local_inputs.insert(local_player_handle, PlayerInput(input));
+        for &handle in local_players.0.iter().skip(1) {
+            // overridden by the bot systems in the rollback schedule
+            local_inputs.insert(handle, PlayerInput(0));
+        }
     }
 
     commands.insert_resource(LocalInputs::<GgrsConfig>(local_inputs));
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::time::TimeUpdateStrategy;
+    use bevy_ggrs::{GgrsApp, GgrsPlugin, GgrsSchedule, ReadInputs};
+
+    use super::*;
+    use crate::{
+        components::{LeaderboardUIRoot, UIRoot},
+        resources::{FrameCount, GameTextures, RollbackDebugState, SessionTimer},
+        systems::{increase_frame_system, show_leaderboard},
+        types::PostFreezeAction,
+    };
+
+    #[test]
+    fn test_training_session_counts_a_round_once() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, GgrsPlugin::<GgrsConfig>::default()))
+            .set_rollback_schedule_fps(FPS as usize)
+            // advance exactly one rollback frame per update
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(
+                1.0 / FPS as f64,
+            )))
+            .add_systems(
+                ReadInputs,
+                |mut commands: Commands, local_players: Res<LocalPlayers>| {
+                    let local_inputs = local_players
+                        .0
+                        .iter()
+                        .map(|&handle| (handle, PlayerInput(0)))
+                        .collect();
+                    commands.insert_resource(LocalInputs::<GgrsConfig>(local_inputs));
+                },
+            )
+            .rollback_resource_with_clone::<SessionRng>()
+            .rollback_resource_with_copy::<FrameCount>()
+            .rollback_resource_with_copy::<GameFreeze>()
+            .rollback_resource_with_copy::<SessionTimer>()
+            .add_systems(
+                GgrsSchedule,
+                (increase_frame_system, show_leaderboard, apply_deferred).chain(),
+            )
+            .add_event::<RoundFinishedEvent>()
+            .insert_resource(SessionRng::new(42))
+            .insert_resource(FrameCount { frame: 0 })
+            .init_resource::<SessionTimer>()
+            .init_resource::<RollbackDebugState>()
+            .init_resource::<GameStats>()
+            .insert_resource(GameConfig::default())
+            .insert_resource(GameTextures {
+                penguin_variants: vec![Handle::default()],
+                ..Default::default()
+            })
+            .insert_resource(Fonts {
+                mono: Handle::default(),
+            })
+            .insert_resource(Leaderboard {
+                scores: [(PlayerID(0), 0), (PlayerID(1), 0)].into_iter().collect(),
+                tournament_format: TournamentFormat::FirstToN(3),
+                score_mode: ScoreMode::ResetBetweenTournaments,
+                rounds_played: 0,
+                tournament_start_scores: HashMap::new(),
+                teams: HashMap::new(),
+            })
+            // the round was just won, the leaderboard is shown once the freeze ends
+            .insert_resource(GameFreeze {
+                end_frame: 2,
+                post_freeze_action: Some(PostFreezeAction::ShowLeaderboard(RoundOutcome::Winner(
+                    PlayerID(0),
+                ))),
+            })
+            .insert_resource(training_session(2));
+        app.world.spawn((Window::default(), PrimaryWindow));
+        app.world.spawn((NodeBundle::default(), UIRoot));
+
+        for _ in 0..10 {
+            app.update();
+        }
+
+        assert!(app.world.resource::<FrameCount>().frame > 2);
+        let leaderboard = app.world.resource::<Leaderboard>();
+        assert_eq!(leaderboard.rounds_played, 1);
+        assert_eq!(leaderboard.scores[&PlayerID(0)], 1);
+        assert_eq!(
+            app.world
+                .query_filtered::<Entity, With<LeaderboardUIRoot>>()
+                .iter(&app.world)
+                .count(),
+            1
+        );
+    }
+}
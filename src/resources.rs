@@ -1,12 +1,30 @@
-use bevy::{ecs as bevy_ecs, prelude::*, text::Font, utils::HashMap};
+use std::{
+    hash::{Hash, Hasher},
+    str::FromStr,
+};
+
+use bevy::{
+    ecs as bevy_ecs,
+    prelude::*,
+    text::Font,
+    utils::{HashMap, HashSet, Instant},
+};
 use bevy_matchbox::matchbox_socket::PeerId;
+use itertools::Itertools;
 use rand::{Rng, SeedableRng};
 use rand_xoshiro::Xoshiro256StarStar;
 
 use crate::{
-    components::Position,
-    constants::COLORS,
-    types::{Cooldown, Direction, ICEServerConfig, PlayerID, PostFreezeAction},
+    components::{hash_option, Item, Position},
+    constants::{
+        COLORS, FPS, ITEM_SPAWN_CHANCE_PERCENTAGE, SEED_COMMITMENT_LENGTH,
+        SLOW_MOTION_RELATIVE_SPEED,
+    },
+    types::{
+        Cooldown, Direction, ICEServerConfig, MapLayout, MapSymmetry, PlayerID, PostFreezeAction,
+        RoundModifier, RoundOutcome, ScoreMode, TournamentFormat,
+    },
+    AppState,
 };
 
 #[derive(Resource)]
@@ -69,6 +87,7 @@ pub struct MapTextures {
     pub wall: Handle<Image>,
     pub destructible_wall: Handle<Image>,
     pub burning_wall: Handle<Image>,
+    pub cracked_ice: Handle<Image>,
 }
 
 #[derive(Resource)]
@@ -80,6 +99,13 @@ pub struct GameTextures {
     pub bombs_up: Handle<Image>,
     pub range_up: Handle<Image>,
     pub bomb_push: Handle<Image>,
+    pub speed_up: Handle<Image>,
+    pub bomb_down: Handle<Image>,
+    pub range_down: Handle<Image>,
+    pub skull: Handle<Image>,
+    pub remote_bomb: Handle<Image>,
+    pub pierce: Handle<Image>,
+    pub magnet: Handle<Image>,
     pub burning_item: Handle<Image>,
     pub trophy: Handle<Image>,
 }
@@ -98,6 +124,38 @@ impl GameTextures {
     }
 }
 
+impl GameTextures {
+    pub fn all_handles(&self) -> Vec<UntypedHandle> {
+        let mut handles = vec![
+            self.bomb.clone().untyped(),
+            self.fire.clone().untyped(),
+            self.bombs_up.clone().untyped(),
+            self.range_up.clone().untyped(),
+            self.bomb_push.clone().untyped(),
+            self.speed_up.clone().untyped(),
+            self.bomb_down.clone().untyped(),
+            self.range_down.clone().untyped(),
+            self.skull.clone().untyped(),
+            self.remote_bomb.clone().untyped(),
+            self.pierce.clone().untyped(),
+            self.magnet.clone().untyped(),
+            self.burning_item.clone().untyped(),
+            self.trophy.clone().untyped(),
+        ];
+        handles.extend(self.penguin_variants.iter().map(|h| h.clone().untyped()));
+        for map_textures in self.map_textures.values() {
+            handles.extend([
+                map_textures.empty.clone().untyped(),
+                map_textures.wall.clone().untyped(),
+                map_textures.destructible_wall.clone().untyped(),
+                map_textures.burning_wall.clone().untyped(),
+                map_textures.cracked_ice.clone().untyped(),
+            ]);
+        }
+        handles
+    }
+}
+
 impl FromWorld for GameTextures {
     fn from_world(world: &mut World) -> Self {
         let asset_server = world.get_resource::<AssetServer>().unwrap();
@@ -116,6 +174,8 @@ impl FromWorld for GameTextures {
                             .load(format!("sprites/world/{}/destructible_wall.png", world_id)),
                         burning_wall: asset_server
                             .load(format!("sprites/world/{}/burning_wall.png", world_id)),
+                        cracked_ice: asset_server
+                            .load(format!("sprites/world/{}/cracked_ice.png", world_id)),
                     },
                 )
             })
@@ -130,6 +190,13 @@ impl FromWorld for GameTextures {
         let bombs_up_texture = asset_server.load("sprites/bombs_up.png");
         let range_up_texture = asset_server.load("sprites/range_up.png");
         let bomb_push_texture = asset_server.load("sprites/bomb_push.png");
+        let speed_up_texture = asset_server.load("sprites/speed_up.png");
+        let bomb_down_texture = asset_server.load("sprites/bomb_down.png");
+        let range_down_texture = asset_server.load("sprites/range_down.png");
+        let skull_texture = asset_server.load("sprites/skull.png");
+        let remote_bomb_texture = asset_server.load("sprites/remote_bomb.png");
+        let pierce_texture = asset_server.load("sprites/pierce.png");
+        let magnet_texture = asset_server.load("sprites/magnet.png");
         let burning_item_texture = asset_server.load("sprites/burning_item.png");
         let trophy_texture = asset_server.load("sprites/trophy.png");
 
@@ -141,19 +208,87 @@ impl FromWorld for GameTextures {
             bombs_up: bombs_up_texture.clone(),
             range_up: range_up_texture.clone(),
             bomb_push: bomb_push_texture.clone(),
+            speed_up: speed_up_texture.clone(),
+            bomb_down: bomb_down_texture.clone(),
+            range_down: range_down_texture.clone(),
+            skull: skull_texture.clone(),
+            remote_bomb: remote_bomb_texture.clone(),
+            pierce: pierce_texture.clone(),
+            magnet: magnet_texture.clone(),
             burning_item: burning_item_texture.clone(),
             trophy: trophy_texture.clone(),
         }
     }
 }
 
-#[derive(Resource, Clone, Copy)]
+// the state entered once all the assets are loaded
+#[derive(Resource)]
+pub struct PostLoadingState(pub AppState);
+
+#[derive(Resource)]
+pub struct LoadingAssets {
+    pub handles: Vec<UntypedHandle>,
+    pub total: usize,
+}
+
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
 pub struct MapSize {
     pub rows: u8,
     pub columns: u8,
 }
 
-#[derive(Resource, Clone, Copy, PartialEq, Eq, Hash)]
+impl MapSize {
+    pub fn from_player_count(n: u8) -> Self {
+        let (rows, columns) = match n {
+            0..=2 => (9, 13),
+            3..=4 => (11, 15),
+            5..=6 => (13, 17),
+            _ => (15, 19),
+        };
+
+        Self { rows, columns }
+    }
+
+    pub fn validate(&self) -> Result<(), &'static str> {
+        if self.rows % 2 == 0 || self.columns % 2 == 0 {
+            // required for the checkered wall pattern
+            return Err("map dimensions must be odd");
+        }
+        if self.rows < 9 || self.columns < 13 {
+            return Err("map must be at least 9x13");
+        }
+        if self.rows > 19 || self.columns > 25 {
+            return Err("map must be at most 19x25");
+        }
+        if self.columns < self.rows {
+            return Err("map must not have more rows than columns");
+        }
+
+        Ok(())
+    }
+}
+
+// parses the `ROWSxCOLS` format
+impl FromStr for MapSize {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (rows, columns) = s
+            .split_once('x')
+            .ok_or_else(|| format!("expected ROWSxCOLS, got: {s}"))?;
+        let parse = |v: &str| v.parse::<u8>().map_err(|e| format!("{v}: {e}"));
+
+        let map_size = Self {
+            rows: parse(rows)?,
+            columns: parse(columns)?,
+        };
+        map_size.validate()?;
+
+        Ok(map_size)
+    }
+}
+
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[allow(clippy::enum_variant_names)]
 pub enum WorldType {
     GrassWorld,
@@ -165,7 +300,7 @@ impl WorldType {
     pub const LIST: [Self; 3] = [Self::GrassWorld, Self::IceWorld, Self::CloudWorld];
 
     pub fn random(rng: &mut SessionRng) -> Self {
-        match rng.gen_u64() % 3 {
+        match rng.gen_range(0, 3) {
             0 => Self::GrassWorld,
             1 => Self::IceWorld,
             2 => Self::CloudWorld,
@@ -173,27 +308,287 @@ impl WorldType {
         }
     }
 
+    pub fn to_byte(self) -> u8 {
+        Self::LIST.iter().position(|&w| w == self).unwrap() as u8
+    }
+
+    pub fn from_byte(byte: u8) -> Option<Self> {
+        Self::LIST.get(byte as usize).copied()
+    }
+
     pub fn next_random(&self, rng: &mut SessionRng) -> Self {
         Self::LIST
             .into_iter()
             .filter(|&w| w != *self)
-            .nth((rng.gen_u64() as usize) % (Self::LIST.len() - 1))
+            .nth(rng.gen_range(0, Self::LIST.len() as u64 - 1) as usize)
             .unwrap()
     }
 }
 
+impl FromStr for WorldType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "grass" => Ok(Self::GrassWorld),
+            "ice" => Ok(Self::IceWorld),
+            "cloud" => Ok(Self::CloudWorld),
+            _ => Err(format!("unknown world type: {s}")),
+        }
+    }
+}
+
+#[derive(Resource, Clone, Copy)]
+pub struct CurrentRoundModifier(pub RoundModifier);
+
 #[derive(Resource)]
 pub struct MatchboxConfig {
     pub number_of_players: u8,
     pub room_id: String,
     pub matchbox_server_url: Option<String>,
     pub ice_server_config: Option<ICEServerConfig>,
+    // `None` disables desync detection
+    pub desync_detection_interval: Option<u32>,
+    // indexed by player handle, missing entries mean no delay
+    // GGRS has a single input delay applied to local players only, so every peer sets the delay of its own player
+    // and the entries of remote players are ignored
+    pub player_input_delays: Vec<usize>,
+    // percentage of outgoing GGRS packets to drop
+    #[cfg(debug_assertions)]
+    pub simulated_packet_loss: u8,
+    // the local vote for the starting world, random if not set
+    pub preferred_world_type: Option<WorldType>,
+    // set in the lobby once all the votes are in
+    pub voted_world_type: Option<WorldType>,
+    // indexed by player handle, free-for-all if not set
+    pub team_assignments: Option<Vec<u8>>,
+}
+
+#[derive(Clone, Copy)]
+pub struct WorldConfig {
+    pub fire_duration_frames: u32,
+    pub item_burn_duration_frames: u32,
+}
+
+#[derive(Resource, Clone)]
+pub struct WorldConfigs {
+    configs: HashMap<WorldType, WorldConfig>,
+}
+
+impl WorldConfigs {
+    pub fn get(&self, world_type: WorldType) -> WorldConfig {
+        self.configs[&world_type]
+    }
+}
+
+impl Default for WorldConfigs {
+    fn default() -> Self {
+        Self {
+            configs: WorldType::LIST
+                .into_iter()
+                .map(|world_type| {
+                    let duration_frames = match world_type {
+                        WorldType::GrassWorld => FPS / 2,
+                        // ice extinguishes fire quickly
+                        WorldType::IceWorld => FPS / 4,
+                        // thin and dry air above the clouds
+                        WorldType::CloudWorld => FPS,
+                    };
+                    (
+                        world_type,
+                        WorldConfig {
+                            fire_duration_frames: duration_frames,
+                            item_burn_duration_frames: duration_frames,
+                        },
+                    )
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Resource, Debug, Clone)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(serde::Deserialize))]
+pub struct LootTable {
+    // item weights
+    pub entries: Vec<(Item, u64)>,
+    // percentage
+    pub spawn_chance: u64,
+}
+
+impl Default for LootTable {
+    fn default() -> Self {
+        Self {
+            entries: vec![
+                (Item::BombsUp, 50),
+                (Item::RangeUp, 40),
+                (Item::BombPush, 10),
+                (Item::SpeedUp, 10),
+                (Item::BombDown, 5),
+                (Item::RangeDown, 5),
+                (Item::Skull, 3),
+                (Item::RemoteBomb, 5),
+                (Item::Pierce, 5),
+                (Item::Magnet, 5),
+            ],
+            spawn_chance: ITEM_SPAWN_CHANCE_PERCENTAGE,
+        }
+    }
+}
+
+impl Hash for LootTable {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (self.entries.len() as u64).hash(state);
+        for &(item, weight) in &self.entries {
+            (item as u8).hash(state);
+            weight.hash(state);
+        }
+        self.spawn_chance.hash(state);
+    }
+}
+
+impl LootTable {
+    // the same table with the curses left out, unless that would leave it empty
+    pub fn without_curses(&self) -> Self {
+        let entries = self
+            .entries
+            .iter()
+            .copied()
+            .filter(|(item, _)| !item.is_curse())
+            .collect::<Vec<_>>();
+        if entries.is_empty() {
+            return self.clone();
+        }
+
+        Self {
+            entries,
+            spawn_chance: self.spawn_chance,
+        }
+    }
+}
+
+// visual only, not part of the game state
+#[derive(Resource)]
+pub struct Confetti {
+    // number of particles left to spawn
+    pub count: u16,
+    pub rng_seed: u64,
+}
+
+// local, per-peer settings which don't affect the game state
+#[derive(Resource, Default)]
+pub struct SettingsResource {
+    pub mouse_input: bool,
+    pub show_rollback_debug: bool,
+    pub gamepad_deadzone: f32,
+}
+
+// all tunable game parameters, every peer sets them from its own options so the lobby makes sure they match
+#[derive(Resource, Clone, Copy)]
+pub struct GameConfig {
+    pub round_duration_secs: u32,
+    pub tournament_format: TournamentFormat,
+    pub score_mode: ScoreMode,
+    pub initial_bombs: u8,
+    pub initial_range: u8,
+    pub bomb_fuse_frames: u32,
+    pub wall_of_death_step_interval: u32,
+    // part of the round in per-mille after which the wall of death starts closing in
+    pub wall_of_death_activation_permille: u32,
+    pub pinata_count: u8,
+    pub overtime_enabled: bool,
+    pub overtime_frames: u32,
+    pub map_layout: MapLayout,
+    pub map_symmetry: MapSymmetry,
+    // part of the passable positions in per-mille covered by destructible walls
+    pub wall_density_permille: u32,
+    // overrides the map size chosen based on the player count
+    pub forced_map_size: Option<MapSize>,
+    // players die on the first hit if not set
+    pub player_hp: Option<u8>,
+    // bombs are only dropped after the action input is held for a while, short taps are cancelled
+    pub hold_to_bomb: bool,
+    pub max_session_frames: Option<u32>,
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        Self {
+            round_duration_secs: 60,
+            tournament_format: TournamentFormat::FirstToN(3),
+            score_mode: ScoreMode::ResetBetweenTournaments,
+            initial_bombs: 1,
+            initial_range: 2,
+            bomb_fuse_frames: 2 * FPS,
+            wall_of_death_step_interval: FPS / 5,
+            wall_of_death_activation_permille: 500,
+            pinata_count: 3,
+            overtime_enabled: false,
+            overtime_frames: 30 * FPS,
+            map_layout: MapLayout::Random,
+            map_symmetry: MapSymmetry::None,
+            wall_density_permille: 400,
+            forced_map_size: None,
+            player_hp: None,
+            hold_to_bomb: false,
+            max_session_frames: None,
+        }
+    }
+}
+
+impl Hash for GameConfig {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.round_duration_secs.hash(state);
+        match self.tournament_format {
+            TournamentFormat::FirstToN(n) => {
+                0u8.hash(state);
+                n.hash(state);
+            }
+            TournamentFormat::BestOfN(n) => {
+                1u8.hash(state);
+                n.hash(state);
+            }
+        }
+        (self.score_mode as u8).hash(state);
+        self.initial_bombs.hash(state);
+        self.initial_range.hash(state);
+        self.bomb_fuse_frames.hash(state);
+        self.wall_of_death_step_interval.hash(state);
+        self.wall_of_death_activation_permille.hash(state);
+        self.pinata_count.hash(state);
+        self.overtime_enabled.hash(state);
+        self.overtime_frames.hash(state);
+        (self.map_layout as u8).hash(state);
+        (self.map_symmetry as u8).hash(state);
+        self.wall_density_permille.hash(state);
+        hash_option(
+            &self
+                .forced_map_size
+                .map(|map_size| (map_size.rows, map_size.columns)),
+            state,
+        );
+        hash_option(&self.player_hp, state);
+        self.hold_to_bomb.hash(state);
+        hash_option(&self.max_session_frames, state);
+    }
 }
 
 #[derive(Resource)]
 pub struct RngSeeds {
     pub local: u64,
+    // identifies the lobby membership of the current commit-reveal round
+    pub round: u64,
+    // the seeds revealed in the current round
     pub remote: HashMap<PeerId, Option<u64>>,
+    // seeds are only revealed after everyone committed to theirs, along with the round of each commitment
+    pub commitments: HashMap<PeerId, (u64, [u8; SEED_COMMITMENT_LENGTH])>,
+    pub revealed_to: HashSet<PeerId>,
+}
+
+#[derive(Resource)]
+pub struct WorldVotes {
+    pub local: WorldType,
+    pub remote: HashMap<PeerId, WorldType>,
 }
 
 // I could not verify it but I assume that the Xoshiro256StarStar generator is platform-independent. This is necessary for cross-platform deterministic gameplay.
@@ -205,19 +600,252 @@ impl SessionRng {
         Self(Xoshiro256StarStar::seed_from_u64(seed))
     }
 
+    // for sessions without peers to exchange seeds with, the seed isn't recorded so they can't be reproduced
+    pub fn from_entropy() -> Self {
+        warn!("Using entropy-seeded RNG: replay is non-deterministic");
+        Self(Xoshiro256StarStar::from_entropy())
+    }
+
     // Allow only `u64` number generation in order to prevent things like generating platform dependent `usize` values.
     pub fn gen_u64(&mut self) -> u64 {
         self.0.gen()
     }
+
+    // uniform in `lo..hi`, values from the biased tail of the `u64` range are rerolled
+    pub fn gen_range(&mut self, lo: u64, hi: u64) -> u64 {
+        assert!(lo < hi, "empty range {lo}..{hi}");
+        let range = hi - lo;
+        let zone = u64::MAX - u64::MAX % range;
+        loop {
+            let value = self.gen_u64();
+            if value < zone {
+                return lo + value % range;
+            }
+        }
+    }
+
+    // true with a probability of `probability_numerator / probability_denominator`
+    pub fn gen_bool(&mut self, probability_numerator: u64, probability_denominator: u64) -> bool {
+        self.gen_range(0, probability_denominator) < probability_numerator
+    }
 }
 
 #[derive(Resource)]
 pub struct LocalPlayerID(pub u8);
 
+// local offline game against bots
+#[derive(Resource, Clone, Copy)]
+pub struct TrainingConfig {
+    pub bots: u8,
+    // the local player is controlled by a bot as well
+    pub bot_player: bool,
+}
+
+#[derive(Resource)]
+pub struct CurrentError(pub String);
+
+// not rollback registered, only slows down cosmetic effects since the virtual clock also drives GGRS
+#[derive(Resource)]
+pub struct SlowMotionEffect {
+    pub end_time: Instant,
+}
+
+impl SlowMotionEffect {
+    pub fn relative_speed(&self) -> f32 {
+        if Instant::now() < self.end_time {
+            SLOW_MOTION_RELATIVE_SPEED
+        } else {
+            1.0
+        }
+    }
+}
+
+// kept across error screens so that the backoff keeps growing until a game starts
+#[derive(Resource)]
+pub struct AutoRetry {
+    pub attempts_remaining: u8,
+    pub next_retry_instant: Instant,
+    pub backoff_secs: u64,
+}
+
+// tournament statistics, rollback registered so mispredicted frames don't count
+#[derive(Resource, Default, Clone)]
+pub struct GameStats {
+    pub longest_chain: HashMap<PlayerID, u32>,
+    pub items_collected: HashMap<PlayerID, u32>,
+    pub tiles_walked: HashMap<PlayerID, u32>,
+}
+
+// sent again for resimulated frames, so readers should only use it for cosmetic effects
+#[derive(Event)]
+pub struct BombExplodedEvent {
+    pub chain_depth: u8,
+}
+
+// sent once the outcome is shown on the leaderboard, sent again if that frame gets resimulated
+#[derive(Event)]
+pub struct RoundFinishedEvent {
+    pub frame: u32,
+    pub round_outcome: RoundOutcome,
+    // set if this round decided the tournament
+    pub tournament_winner: Option<PlayerID>,
+}
+
+// cleared at the start of every rollback frame, so only readers in the rollback schedule see it
+#[derive(Event)]
+pub struct WallOfDeathDone {
+    pub frame: u32,
+}
+
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(serde::Serialize))]
+#[cfg_attr(not(target_arch = "wasm32"), serde(tag = "kind"))]
+pub enum GameEventKind {
+    PlayerMoved { player_id: u8, y: u8, x: u8 },
+    BombDropped { player_id: u8, y: u8, x: u8 },
+    BombExploded { owner: Option<u8>, y: u8, x: u8 },
+    PlayerDied { player_id: u8, y: u8, x: u8 },
+    ItemPickedUp { player_id: u8, item: Item },
+    WallCrumbled { y: u8, x: u8 },
+    WallOfDeathDone,
+}
+
+impl GameEventKind {
+    // events with a level higher than the configured one are not logged
+    pub fn level(&self) -> u8 {
+        match self {
+            GameEventKind::PlayerDied { .. } => 1,
+            GameEventKind::BombDropped { .. }
+            | GameEventKind::BombExploded { .. }
+            | GameEventKind::ItemPickedUp { .. }
+            | GameEventKind::WallCrumbled { .. }
+            | GameEventKind::WallOfDeathDone => 2,
+            GameEventKind::PlayerMoved { .. } => 3,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(not(target_arch = "wasm32"), derive(serde::Serialize))]
+pub struct GameEvent {
+    pub frame: u32,
+    #[cfg_attr(not(target_arch = "wasm32"), serde(flatten))]
+    pub kind: GameEventKind,
+}
+
+// only present when game event logging is enabled, resimulated frames push their events again
+#[derive(Resource, Default)]
+pub struct GameEventQueue(pub Vec<GameEvent>);
+
+impl GameEventQueue {
+    pub fn push(&mut self, frame: u32, kind: GameEventKind) {
+        self.0.push(GameEvent { frame, kind });
+    }
+}
+
+#[derive(Resource)]
+pub struct CurrentTip(pub String);
+
 #[derive(Resource)]
 pub struct Leaderboard {
-    pub scores: HashMap<PlayerID, u8>,
-    pub winning_score: u8,
+    // career totals in the cumulative score mode
+    pub scores: HashMap<PlayerID, u32>,
+    pub tournament_format: TournamentFormat,
+    pub score_mode: ScoreMode,
+    pub rounds_played: u32,
+    // snapshot of the scores taken when the current tournament started
+    pub tournament_start_scores: HashMap<PlayerID, u32>,
+    // empty in free-for-all matches, teammates share their round wins
+    pub teams: HashMap<PlayerID, u8>,
+}
+
+impl Leaderboard {
+    // a team's score is kept under its lowest player ID, the entries of the other teammates stay unused
+    pub fn score_holder(&self, player_id: PlayerID) -> PlayerID {
+        match self.teams.get(&player_id) {
+            Some(team) => self
+                .teams
+                .iter()
+                .filter(|(_, player_team)| *player_team == team)
+                .map(|(&teammate, _)| teammate)
+                .min_by_key(|teammate| teammate.0)
+                .unwrap(),
+            None => player_id,
+        }
+    }
+
+    pub fn add_round_win(&mut self, player_id: PlayerID) {
+        let score = self.scores.get_mut(&self.score_holder(player_id)).unwrap();
+        *score = score.saturating_add(1);
+    }
+
+    pub fn total_score(&self, player_id: PlayerID) -> u32 {
+        self.scores[&self.score_holder(player_id)]
+    }
+
+    pub fn tournament_score(&self, player_id: PlayerID) -> u8 {
+        let score_holder = self.score_holder(player_id);
+        let start_score = self
+            .tournament_start_scores
+            .get(&score_holder)
+            .copied()
+            .unwrap_or(0);
+        // a tournament ends before any side reaches `u8::MAX` wins
+        (self.scores[&score_holder] - start_score) as u8
+    }
+
+    // every player with the score of their side
+    pub fn tournament_scores(&self) -> HashMap<PlayerID, u8> {
+        self.scores
+            .keys()
+            .map(|&player_id| (player_id, self.tournament_score(player_id)))
+            .collect()
+    }
+
+    // one entry per side under its score holder, ordered by player ID
+    pub fn side_scores(&self) -> Vec<(PlayerID, u8)> {
+        self.scores
+            .keys()
+            .filter(|&&player_id| self.score_holder(player_id) == player_id)
+            .map(|&player_id| (player_id, self.tournament_score(player_id)))
+            .sorted_by_key(|(player_id, _)| player_id.0)
+            .collect()
+    }
+
+    // the score holder of the side that leads, ties go to the lower player ID
+    pub fn leader(&self) -> PlayerID {
+        self.side_scores()
+            .into_iter()
+            .max_by_key(|&(player_id, score)| (score, u8::MAX - player_id.0))
+            .map(|(player_id, _)| player_id)
+            .unwrap()
+    }
+
+    pub fn is_same_side(&self, player_id: PlayerID, other_player_id: PlayerID) -> bool {
+        self.score_holder(player_id) == self.score_holder(other_player_id)
+    }
+
+    pub fn tournament_winner(&self) -> Option<PlayerID> {
+        let side_scores = self.side_scores();
+        match self.tournament_format {
+            TournamentFormat::FirstToN(n) => side_scores
+                .iter()
+                .find(|(_, score)| *score >= n)
+                .map(|&(player_id, _)| player_id),
+            TournamentFormat::BestOfN(n) => {
+                if self.rounds_played < n as u32 {
+                    return None;
+                }
+
+                let max_score = side_scores.iter().map(|(_, score)| *score).max()?;
+                let mut leaders = side_scores.iter().filter(|(_, score)| *score == max_score);
+                match (leaders.next(), leaders.next()) {
+                    (Some(&(leader, _)), None) => Some(leader),
+                    _ => None,
+                }
+            }
+        }
+    }
 }
 
 #[derive(Resource, Clone, Copy)]
@@ -225,6 +853,64 @@ pub struct FrameCount {
     pub frame: u32,
 }
 
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub enum DesyncStatus {
+    #[default]
+    Ok,
+    Checking,
+    Desynced,
+}
+
+// local debug information, not part of the game state
+#[derive(Resource, Default)]
+pub struct RollbackDebugState {
+    pub rollbacks_this_second: u32,
+    pub last_rollback_frame: u32,
+    pub frames_behind: u32,
+    pub desync_status: DesyncStatus,
+    // rollbacks counted since the display was last updated
+    pub pending_rollbacks: u32,
+    pub last_simulated_frame: u32,
+}
+
+// solid entities by position, rebuilt by `sync_map_system` on every simulated frame so it doesn't need rolling back
+// the entries go stale once solids move or despawn, so it is synced right before the systems reading it
+#[derive(Resource, Default)]
+pub struct Map {
+    pub solids: HashMap<Position, Entity>,
+}
+
+// local, only used to show where players were before a rollback corrected them
+#[derive(Resource, Default)]
+pub struct GhostPositionState {
+    // player positions of the recently simulated frames, compared again when a frame is resimulated
+    pub positions: HashMap<u32, HashMap<PlayerID, Position>>,
+    // predicted positions that the last resimulation corrected
+    pub corrections: HashMap<PlayerID, Position>,
+}
+
+// kept across tournaments, rollback registered so the session duration limit is reached on the same frame everywhere
+#[derive(Resource, Default, Clone, Copy)]
+pub struct SessionTimer {
+    pub total_frames: u32,
+}
+
+// the session duration limit was reached, no new tournament is started after the running one
+#[derive(Resource, Clone, Copy)]
+pub struct SessionTimeUp;
+
+#[derive(Resource, Default)]
+pub struct FrameInterpolation {
+    pub last_frame: u32,
+    // progress from the previous to the current simulated frame (0 - 1)
+    pub fraction: f32,
+}
+
+// positions filled by the wall of death, recorded when the walls spawn
+// not rollback registered, the wall of death doesn't depend on inputs so its progress is never corrected
+#[derive(Resource, Default)]
+pub struct WallOfDeathConsumed(pub HashSet<Position>);
+
 #[derive(Resource, Clone, Copy)]
 pub enum WallOfDeath {
     Dormant {
@@ -238,11 +924,134 @@ pub enum WallOfDeath {
     Done,
 }
 
-#[derive(Resource)]
+#[derive(Resource, Clone, Copy)]
 pub struct GameEndFrame(pub u32);
 
+#[derive(Resource, Clone, Copy)]
+pub struct OvertimeActive {
+    pub announcement_frame: u32,
+}
+
+// maximum number of hops a burning item can spread to adjacent items
+#[derive(Resource, Clone, Copy)]
+pub struct ChainBurnDepth(pub u8);
+
+impl Default for ChainBurnDepth {
+    fn default() -> Self {
+        Self(3)
+    }
+}
+
 #[derive(Resource, Clone, Copy)]
 pub struct GameFreeze {
     pub end_frame: u32,
     pub post_freeze_action: Option<PostFreezeAction>,
 }
+
+// diagnostics for `check_game_rules`, not rolled back
+// resimulated frames overwrite their pending count, which is only added to the total once the frame is confirmed
+#[derive(Resource, Default)]
+pub struct GameIntegrityViolation {
+    pub frame: u32,
+    pub count: u32,
+    pub total: u32,
+    pub pending: HashMap<u32, u32>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_map_size_from_player_count() {
+        let map_size = |rows, columns| MapSize { rows, columns };
+
+        assert_eq!(MapSize::from_player_count(2), map_size(9, 13));
+        assert_eq!(MapSize::from_player_count(3), map_size(11, 15));
+        assert_eq!(MapSize::from_player_count(4), map_size(11, 15));
+        assert_eq!(MapSize::from_player_count(5), map_size(13, 17));
+        assert_eq!(MapSize::from_player_count(6), map_size(13, 17));
+        assert_eq!(MapSize::from_player_count(7), map_size(15, 19));
+        assert_eq!(MapSize::from_player_count(8), map_size(15, 19));
+    }
+
+    #[test]
+    fn test_map_size_from_str() {
+        assert_eq!(
+            "11x15".parse(),
+            Ok(MapSize {
+                rows: 11,
+                columns: 15
+            })
+        );
+        assert!("11".parse::<MapSize>().is_err());
+        assert!("11xabc".parse::<MapSize>().is_err());
+        assert!("10x15".parse::<MapSize>().is_err());
+    }
+
+    #[test]
+    fn test_map_size_validate() {
+        let map_size = |rows, columns| MapSize { rows, columns };
+
+        assert!(map_size(9, 13).validate().is_ok());
+        assert!(map_size(19, 25).validate().is_ok());
+        assert!(map_size(15, 15).validate().is_ok());
+        for n in 2..=8 {
+            assert!(MapSize::from_player_count(n).validate().is_ok());
+        }
+
+        assert!(map_size(10, 13).validate().is_err());
+        assert!(map_size(9, 14).validate().is_err());
+        assert!(map_size(7, 13).validate().is_err());
+        assert!(map_size(9, 11).validate().is_err());
+        assert!(map_size(21, 25).validate().is_err());
+        assert!(map_size(19, 27).validate().is_err());
+        assert!(map_size(17, 15).validate().is_err());
+    }
+
+    #[test]
+    fn test_session_rng_gen_range_bounds() {
+        let mut rng = SessionRng::new(0);
+        for _ in 0..1000 {
+            let value = rng.gen_range(10, 13);
+            assert!((10..13).contains(&value));
+        }
+        assert_eq!(rng.gen_range(7, 8), 7);
+        // the rejection zone is largest for ranges just over half of `u64::MAX`
+        let value = rng.gen_range(0, u64::MAX / 2 + 2);
+        assert!(value < u64::MAX / 2 + 2);
+    }
+
+    #[test]
+    fn test_session_rng_gen_range_is_uniform() {
+        const BUCKETS: u64 = 6;
+        const SAMPLES: u64 = 60_000;
+
+        let mut rng = SessionRng::new(1234);
+        let mut counts = [0u64; BUCKETS as usize];
+        for _ in 0..SAMPLES {
+            counts[rng.gen_range(0, BUCKETS) as usize] += 1;
+        }
+
+        let expected = (SAMPLES / BUCKETS) as f64;
+        let chi_squared: f64 = counts
+            .iter()
+            .map(|&count| (count as f64 - expected).powi(2) / expected)
+            .sum();
+        // critical value for 5 degrees of freedom at p = 0.001
+        assert!(
+            chi_squared < 20.515,
+            "chi-squared {chi_squared} for {counts:?}"
+        );
+    }
+
+    #[test]
+    fn test_session_rng_gen_bool() {
+        let mut rng = SessionRng::new(42);
+        assert!((0..100).all(|_| !rng.gen_bool(0, 100)));
+        assert!((0..100).all(|_| rng.gen_bool(100, 100)));
+
+        let hits = (0..30_000).filter(|_| rng.gen_bool(1, 3)).count();
+        assert!((9_500..10_500).contains(&hits), "{hits} hits");
+    }
+}
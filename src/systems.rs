@@ -1,10 +1,13 @@
+use std::time::Duration;
+
 use bevy::{
+    asset::LoadState,
     prelude::*,
-    utils::{HashMap, HashSet},
+    utils::{HashMap, HashSet, Instant},
     window::PrimaryWindow,
 };
 use bevy_ggrs::{
-    ggrs::{PlayerType, SessionBuilder},
+    ggrs::{DesyncDetection, PlayerType, SessionBuilder},
     AddRollbackCommandExtension, PlayerInputs, Rollback, RollbackOrdered, Session,
 };
 use bevy_matchbox::{
@@ -13,30 +16,177 @@ use bevy_matchbox::{
     MatchboxSocket,
 };
 use itertools::Itertools;
+use rand::{rngs::StdRng, Rng, SeedableRng};
 
+#[cfg(debug_assertions)]
+use crate::types::LossyChannel;
 use crate::{
     components::*,
     constants::{
-        BOMB_SHORTENED_FUSE_FRAME_COUNT, BOMB_Z_LAYER, COLORS, FIRE_Z_LAYER, FPS,
-        GAME_START_FREEZE_FRAME_COUNT, GET_READY_DISPLAY_FRAME_COUNT, HUD_HEIGHT, INPUT_ACTION,
-        INPUT_DOWN, INPUT_LEFT, INPUT_RIGHT, INPUT_UP, ITEM_SPAWN_CHANCE_PERCENTAGE,
-        LEADERBOARD_DISPLAY_FRAME_COUNT, MAX_PREDICTED_FRAMES, MOVING_OBJECT_FRAME_INTERVAL,
-        PIXEL_SCALE, TILE_HEIGHT, TILE_WIDTH, TOURNAMENT_WINNER_DISPLAY_FRAME_COUNT, WALL_Z_LAYER,
+        BOMB_FLASH_FRAMES, BOMB_FLASH_INTERVAL, BOMB_HOLD_FRAMES, BOMB_SHORTENED_FUSE_FRAME_COUNT,
+        BOMB_WARNING_FRAMES, BOMB_Z_LAYER, CAMERA_SHAKE_DECAY,
+        CAMERA_SHAKE_INTENSITY_PER_CHAIN_DEPTH, COLORS, CONFETTI_COLORS, CONFETTI_COUNT,
+        CRACKABLE_ICE_Z_LAYER, DEATH_BLINK_INTERVAL, EMOTE_COMBO_WINDOW_FRAMES,
+        EMOTE_DURATION_FRAMES, EMOTE_SYMBOLS, FIRE_Z_LAYER, FPS, GAMEPLAY_TIPS,
+        GAME_START_FREEZE_FRAME_COUNT, GET_READY_DISPLAY_FRAME_COUNT, GHOST_DISPLAY_FRAMES,
+        HUD_HEIGHT, INITIAL_AUTO_RETRY_BACKOFF_SECS, INPUT_ACTION, INPUT_ACTION_HELD,
+        INPUT_DETONATE, INPUT_DOWN, INPUT_EMOTE_1, INPUT_EMOTE_2, INPUT_LEFT, INPUT_RIGHT,
+        INPUT_UP, LEADERBOARD_DISPLAY_FRAME_COUNT, MAGNET_RADIUS_PER_ITEM, MAX_AUTO_RETRIES,
+        MAX_BOMB_WARNING_BOMBS, MAX_CAMERA_SHAKE_INTENSITY, MAX_CHAIN_DEPTH,
+        MAX_CONFETTI_PARTICLES, MAX_FIRE_ENTITIES, MAX_MAGNET_RADIUS, MAX_PREDICTED_FRAMES,
+        MIN_PLAYER_MOVE_FRAME_INTERVAL, MOVING_OBJECT_FRAME_INTERVAL, OVERTIME_MESSAGE_FRAME_COUNT,
+        PENDING_BOMB_WINDOW, PIXEL_SCALE, PLAYER_Z_LAYER, PROTOCOL_VERSION, RESPAWN_INVULN_FRAMES,
+        RNG_SEED_COMMIT_PACKET, RNG_SEED_REVEAL_PACKET, SATCHEL_DROP_MAX_ITEM_COUNT,
+        SEED_COMMITMENT_LENGTH, SKULL_STUN_DURATION_FRAMES, SLOWED_MOVE_FRAME_DELAY,
+        SLOW_DURATION_FRAMES, SLOW_MOTION_DURATION_SECS, SPEED_ROUND_MOVE_INTERVAL_DIVISOR,
+        TILE_HEIGHT, TILE_WIDTH, TOURNAMENT_WINNER_DISPLAY_FRAME_COUNT, VICTORY_FRAME_INTERVAL,
+        WALL_Z_LAYER, WORLD_VOTE_PACKET,
     },
     resources::*,
-    types::{Direction, PlayerID, PostFreezeAction, RoundOutcome},
+    types::{
+        Direction, PlayerID, PostFreezeAction, RoundModifier, RoundOutcome, ScoreMode, SkullEffect,
+    },
     utils::{
-        burn_item, decode, format_hud_time, generate_item_at_position, get_x, get_y,
-        setup_fullscreen_message_display, setup_get_ready_display, setup_leaderboard_display,
-        setup_round, setup_tournament_winner_display, shuffle,
+        blast_positions, bomb_tint, burn_item, config_fingerprint, confirmed_frame, decode,
+        format_hud_time, generate_item_at_position, get_x, get_y, lobby_round, seed_commitment,
+        setup_freeze_message_display, setup_fullscreen_message_display, setup_get_ready_display,
+        setup_leaderboard_display, setup_lobby_portraits, setup_map_view, setup_retry_button,
+        setup_room_url_display, setup_round, setup_tournament_winner_display, shuffle, spawn_item,
     },
     AppState, GgrsConfig,
 };
 
+pub fn slow_motion_system(
+    mut commands: Commands,
+    slow_motion_effect: Option<Res<SlowMotionEffect>>,
+) {
+    if let Some(slow_motion_effect) = slow_motion_effect {
+        if Instant::now() >= slow_motion_effect.end_time {
+            commands.remove_resource::<SlowMotionEffect>();
+        }
+    }
+}
+
+pub fn camera_shake_system(
+    time: Res<Time>,
+    slow_motion_effect: Option<Res<SlowMotionEffect>>,
+    mut bomb_exploded_events: EventReader<BombExplodedEvent>,
+    mut camera_query: Query<(&mut Transform, &mut CameraShake)>,
+) {
+    let Ok((mut transform, mut camera_shake)) = camera_query.get_single_mut() else {
+        return;
+    };
+
+    // deeper chains shake harder, resent events of resimulated frames don't stack
+    for event in bomb_exploded_events.read() {
+        let intensity = ((event.chain_depth as f32 + 1.0) * CAMERA_SHAKE_INTENSITY_PER_CHAIN_DEPTH)
+            .min(MAX_CAMERA_SHAKE_INTENSITY);
+        camera_shake.intensity = camera_shake.intensity.max(intensity);
+    }
+
+    let relative_speed = slow_motion_effect.map_or(1.0, |s| s.relative_speed());
+    camera_shake.intensity = (camera_shake.intensity
+        - CAMERA_SHAKE_DECAY * time.delta_seconds() * relative_speed)
+        .max(0.0);
+
+    let t = time.elapsed_seconds() * relative_speed;
+    let offset = Vec3::new((t * 50.0).sin(), (t * 37.0).cos(), 0.0) * camera_shake.intensity;
+    transform.translation = camera_shake.origin + offset.round();
+}
+
+pub fn begin_frame_interpolation(
+    mut commands: Commands,
+    time: Res<Time>,
+    frame_count: Res<FrameCount>,
+    mut frame_interpolation: ResMut<FrameInterpolation>,
+    new_entity_query: Query<
+        (Entity, &Transform),
+        (
+            Or<(With<Player>, With<Bomb>, With<Fire>)>,
+            Without<InterpolatedTransform>,
+        ),
+    >,
+    mut interpolated_transform_query: Query<&mut InterpolatedTransform>,
+) {
+    for (entity, transform) in new_entity_query.iter() {
+        commands.entity(entity).insert(InterpolatedTransform {
+            previous: transform.translation,
+            rendered: transform.translation,
+        });
+    }
+
+    // the simulation advanced (or was rolled back), continue from what is on screen
+    if frame_count.frame != frame_interpolation.last_frame {
+        frame_interpolation.last_frame = frame_count.frame;
+        frame_interpolation.fraction = 0.0;
+        for mut interpolated_transform in interpolated_transform_query.iter_mut() {
+            interpolated_transform.previous = interpolated_transform.rendered;
+        }
+    }
+
+    frame_interpolation.fraction =
+        (frame_interpolation.fraction + time.delta_seconds() * FPS as f32).min(1.0);
+}
+
+pub fn apply_interpolation(
+    frame_interpolation: Res<FrameInterpolation>,
+    mut interpolated_query: Query<(
+        &Transform,
+        &mut GlobalTransform,
+        &mut InterpolatedTransform,
+        Option<&Children>,
+    )>,
+    mut child_query: Query<
+        (&Transform, &mut GlobalTransform, Option<&Children>),
+        Without<InterpolatedTransform>,
+    >,
+) {
+    for (transform, mut global_transform, mut interpolated_transform, children) in
+        interpolated_query.iter_mut()
+    {
+        let translation = interpolated_transform
+            .previous
+            .lerp(transform.translation, frame_interpolation.fraction);
+        interpolated_transform.rendered = translation;
+        *global_transform = GlobalTransform::from(Transform {
+            translation,
+            ..*transform
+        });
+
+        // the children were already propagated from the simulated transform, so they would lag behind
+        if let Some(children) = children {
+            propagate_interpolated_transform(*global_transform, children, &mut child_query);
+        }
+    }
+}
+
+fn propagate_interpolated_transform(
+    parent_global_transform: GlobalTransform,
+    children: &[Entity],
+    child_query: &mut Query<
+        (&Transform, &mut GlobalTransform, Option<&Children>),
+        Without<InterpolatedTransform>,
+    >,
+) {
+    for &child in children {
+        let Ok((transform, mut global_transform, grandchildren)) = child_query.get_mut(child)
+        else {
+            continue;
+        };
+        *global_transform = parent_global_transform.mul_transform(*transform);
+
+        let global_transform = *global_transform;
+        if let Some(grandchildren) = grandchildren.map(|grandchildren| grandchildren.to_vec()) {
+            propagate_interpolated_transform(global_transform, &grandchildren, child_query);
+        }
+    }
+}
+
 pub fn print_network_stats_system(
     time: Res<Time>,
     mut network_stats_cooldown: ResMut<NetworkStatsCooldown>,
     session: Option<Res<Session<GgrsConfig>>>,
+    game_freeze: Option<Res<GameFreeze>>,
     mut network_stats_text_query: Query<&mut Text, With<NetworkStatsDisplay>>,
 ) {
     let mut text = if let Ok(text) = network_stats_text_query.get_single_mut() {
@@ -46,8 +196,15 @@ pub fn print_network_stats_system(
         return;
     };
 
+    // the stats would be misleading while the game clock is frozen
+    if game_freeze.is_some() {
+        network_stats_cooldown.cooldown.pause();
+    } else {
+        network_stats_cooldown.cooldown.resume();
+    }
+
     network_stats_cooldown.cooldown.tick(time.delta());
-    if network_stats_cooldown.cooldown.trigger() {
+    if network_stats_cooldown.cooldown.just_fired() {
         let print_stats_to_console = if network_stats_cooldown.print_cooldown == 0 {
             // print to console on every second update in order not to spam
             network_stats_cooldown.print_cooldown = 1;
@@ -84,50 +241,248 @@ pub fn print_network_stats_system(
                         .join("\n");
                     text.sections[0].value = stats_text;
                 }
-                _ => unreachable!(),
+                // there is no network in training mode
+                _ => (),
+            }
+        }
+    }
+}
+
+pub fn confetti_system(
+    mut commands: Commands,
+    confetti: Option<ResMut<Confetti>>,
+    mut rng: Local<Option<StdRng>>,
+    primary_window_query: Query<&Window, With<PrimaryWindow>>,
+    mut particle_query: Query<(Entity, &ConfettiParticle, &mut Style, &mut Transform)>,
+) {
+    let Some(mut confetti) = confetti else {
+        *rng = None;
+        return;
+    };
+    if confetti.is_added() || rng.is_none() {
+        *rng = Some(StdRng::seed_from_u64(confetti.rng_seed));
+    }
+    let rng = rng.as_mut().unwrap();
+    let Ok(window) = primary_window_query.get_single() else {
+        return;
+    };
+
+    let mut particle_count = 0;
+    for (entity, particle, mut style, mut transform) in particle_query.iter_mut() {
+        let (Val::Px(top), Val::Px(left)) = (style.top, style.left) else {
+            continue;
+        };
+
+        if top > window.height() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+
+        style.top = Val::Px(top + particle.fall_speed);
+        style.left = Val::Px(left + particle.drift);
+        transform.rotate_z(particle.rotation_speed);
+        particle_count += 1;
+    }
+
+    // spawn a few new particles each frame
+    for _ in 0..2 {
+        if confetti.count == 0 || particle_count >= MAX_CONFETTI_PARTICLES {
+            break;
+        }
+
+        let color = COLORS[CONFETTI_COLORS[rng.gen_range(0..CONFETTI_COLORS.len())]];
+        commands.spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(-(PIXEL_SCALE as f32)),
+                    left: Val::Px(rng.gen_range(0.0..window.width())),
+                    width: Val::Px(PIXEL_SCALE as f32),
+                    height: Val::Px(PIXEL_SCALE as f32),
+                    ..Default::default()
+                },
+                background_color: color.into(),
+                z_index: ZIndex::Global(2),
+                ..Default::default()
+            },
+            ConfettiParticle {
+                fall_speed: rng.gen_range(1.0..4.0),
+                drift: rng.gen_range(-1.0..1.0),
+                rotation_speed: rng.gen_range(-0.2..0.2),
+            },
+            UIComponent,
+        ));
+
+        confetti.count -= 1;
+        particle_count += 1;
+    }
+}
+
+pub fn setup_loading(
+    mut commands: Commands,
+    game_textures: Res<GameTextures>,
+    fonts: Res<Fonts>,
+    primary_window_query: Query<&Window, With<PrimaryWindow>>,
+) {
+    // the assets were already requested when their resources got initialized
+    let mut handles = game_textures.all_handles();
+    handles.push(fonts.mono.clone().untyped());
+    let total = handles.len();
+    commands.insert_resource(LoadingAssets { handles, total });
+
+    commands.spawn(Camera2dBundle::default());
+
+    let window = primary_window_query.single();
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(window.height() / 2.0 - PIXEL_SCALE as f32),
+                left: Val::Px(window.width() / 4.0),
+                width: Val::Px(window.width() / 2.0),
+                height: Val::Px(2.0 * PIXEL_SCALE as f32),
+                ..Default::default()
+            },
+            background_color: COLORS[8].into(),
+            ..Default::default()
+        })
+        .with_children(|parent| {
+            parent.spawn((
+                NodeBundle {
+                    style: Style {
+                        width: Val::Percent(0.0),
+                        height: Val::Percent(100.0),
+                        ..Default::default()
+                    },
+                    background_color: COLORS[15].into(),
+                    ..Default::default()
+                },
+                LoadingProgressBar,
+            ));
+        });
+}
+
+pub fn check_loading(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    loading_assets: Res<LoadingAssets>,
+    mut progress_bar_query: Query<&mut Style, With<LoadingProgressBar>>,
+    post_loading_state: Res<PostLoadingState>,
+    mut app_state: ResMut<NextState<AppState>>,
+) {
+    let mut loaded_count = 0;
+    for handle in &loading_assets.handles {
+        match asset_server.get_load_state(handle.id()) {
+            Some(LoadState::Loaded) => loaded_count += 1,
+            Some(LoadState::Failed) => {
+                error!("Failed to load asset: {:?}", handle.path());
+                commands.insert_resource(CurrentError("FAILED TO LOAD ASSETS!".to_string()));
+                app_state.set(AppState::Error);
+                return;
             }
+            _ => (),
         }
     }
+
+    if let Ok(mut style) = progress_bar_query.get_single_mut() {
+        style.width = Val::Percent(loaded_count as f32 / loading_assets.total as f32 * 100.0);
+    }
+
+    if loaded_count == loading_assets.total {
+        info!("Loaded all {loaded_count} assets.");
+        app_state.set(post_loading_state.0);
+    }
+}
+
+pub fn teardown_loading(
+    mut commands: Commands,
+    teardown_entities_query: Query<Entity, Without<Window>>,
+) {
+    commands.remove_resource::<LoadingAssets>();
+    teardown_entities_query
+        .iter()
+        .for_each(|e| commands.entity(e).despawn());
 }
 
 pub fn setup_lobby(
     mut commands: Commands,
     matchbox_config: Res<MatchboxConfig>,
+    game_config: Res<GameConfig>,
     fonts: Res<Fonts>,
+    rendered_frame_count: Res<bevy::core::FrameCount>,
     mut primary_window_query: Query<&mut Window, With<PrimaryWindow>>,
+    mut app_state: ResMut<NextState<AppState>>,
 ) {
     // choose map size based on player count
-    let map_size = if matchbox_config.number_of_players > 4 {
-        MapSize {
-            rows: 13,
-            columns: 17,
-        }
-    } else {
-        MapSize {
-            rows: 9,
-            columns: 13,
-        }
-    };
+    let map_size = game_config
+        .forced_map_size
+        .unwrap_or_else(|| MapSize::from_player_count(matchbox_config.number_of_players));
+    if let Err(e) = map_size.validate() {
+        error!("Invalid map size {map_size:?}: {e}");
+        commands.insert_resource(CurrentError("INVALID MAP SIZE!".to_string()));
+        app_state.set(AppState::Error);
+        return;
+    }
     commands.insert_resource(map_size);
 
-    // resize window based on map size
     let mut window = primary_window_query.single_mut();
-    window.resolution.set(
-        (map_size.columns as u32 * TILE_WIDTH) as f32,
-        (HUD_HEIGHT + map_size.rows as u32 * TILE_HEIGHT) as f32,
+    setup_map_view(&mut commands, &mut window, map_size);
+
+    setup_fullscreen_message_display(
+        &mut commands,
+        &window,
+        &fonts,
+        "Entering lobby...",
+        rendered_frame_count.0,
+    );
+    setup_room_url_display(&mut commands, &window, &fonts, &matchbox_config.room_id);
+    setup_lobby_portraits(
+        &mut commands,
+        &window,
+        &fonts,
+        matchbox_config.number_of_players,
     );
+}
 
-    // spawn the main camera
-    commands.spawn(Camera2dBundle {
-        transform: Transform::from_xyz(
-            ((map_size.columns as u32 * TILE_WIDTH) as f32) / 2.0,
-            -((map_size.rows as u32 * TILE_HEIGHT - HUD_HEIGHT) as f32 / 2.0),
-            999.9,
-        ),
-        ..default()
-    });
+pub fn room_url_display_system(
+    matchbox_config: Res<MatchboxConfig>,
+    rendered_frame_count: Res<bevy::core::FrameCount>,
+    mut room_url_display_query: Query<(&mut Text, &mut RoomUrlDisplay)>,
+) {
+    for (mut text, mut room_url_display) in room_url_display_query.iter_mut() {
+        let Some(end_frame) = room_url_display.copied_message_end_frame else {
+            continue;
+        };
+
+        if rendered_frame_count.0 >= end_frame {
+            text.sections[0].value = format!("Room: {}", matchbox_config.room_id);
+            room_url_display.copied_message_end_frame = None;
+        }
+    }
+}
+
+pub fn fade_in_system(
+    mut commands: Commands,
+    rendered_frame_count: Res<bevy::core::FrameCount>,
+    mut fade_in_query: Query<(Entity, &FadeIn, &mut BackgroundColor)>,
+    mut text_query: Query<&mut Text, With<FullscreenMessageText>>,
+) {
+    for (entity, fade_in, mut background_color) in fade_in_query.iter_mut() {
+        let elapsed_frames = rendered_frame_count.0.saturating_sub(fade_in.start_frame);
+        let alpha =
+            elapsed_frames.min(fade_in.duration_frames) as f32 / fade_in.duration_frames as f32;
+
+        background_color.0 = Color::from(COLORS[0]).with_a(alpha);
+        for mut text in text_query.iter_mut() {
+            for section in text.sections.iter_mut() {
+                section.style.color.set_a(alpha);
+            }
+        }
 
-    setup_fullscreen_message_display(&mut commands, &window, &fonts, "Entering lobby...");
+        if elapsed_frames >= fade_in.duration_frames {
+            commands.entity(entity).remove::<FadeIn>();
+        }
+    }
 }
 
 pub fn start_matchbox_socket(mut commands: Commands, matchbox_config: Res<MatchboxConfig>) {
@@ -159,33 +514,68 @@ pub fn start_matchbox_socket(mut commands: Commands, matchbox_config: Res<Matchb
             .build(),
     ));
 
-    let local_seed = rand::random();
-    info!("Generated the local RNG seed: {local_seed}");
+    // the local seed is generated when the first commit-reveal round starts
     commands.insert_resource(RngSeeds {
-        local: local_seed,
+        local: 0,
+        round: 0,
+        remote: HashMap::with_capacity(matchbox_config.number_of_players as usize - 1),
+        commitments: HashMap::with_capacity(matchbox_config.number_of_players as usize - 1),
+        revealed_to: HashSet::with_capacity(matchbox_config.number_of_players as usize - 1),
+    });
+
+    let local_world_vote = matchbox_config
+        .preferred_world_type
+        .unwrap_or_else(|| WorldType::LIST[rand::random::<usize>() % WorldType::LIST.len()]);
+    info!("Voting for the starting world: {local_world_vote:?}");
+    commands.insert_resource(WorldVotes {
+        local: local_world_vote,
         remote: HashMap::with_capacity(matchbox_config.number_of_players as usize - 1),
     });
 }
 
 pub fn lobby_system(
     mut app_state: ResMut<NextState<AppState>>,
-    matchbox_config: Res<MatchboxConfig>,
+    mut matchbox_config: ResMut<MatchboxConfig>,
     mut socket: ResMut<MatchboxSocket<MultipleChannels>>,
     mut rng_seeds: ResMut<RngSeeds>,
+    mut world_votes: ResMut<WorldVotes>,
+    game_config: Res<GameConfig>,
+    loot_table: Res<LootTable>,
     mut commands: Commands,
     primary_window_query: Query<&Window, With<PrimaryWindow>>,
     mut info_text_query: Query<(&mut Text, &mut Style), With<FullscreenMessageText>>,
+    game_textures: Res<GameTextures>,
+    mut lobby_portrait_query: Query<(
+        &LobbyPortrait,
+        &Children,
+        &mut BackgroundColor,
+        &mut BorderColor,
+    )>,
+    mut lobby_portrait_image_query: Query<(&mut UiImage, &mut Visibility)>,
+    mut lobby_portrait_text_query: Query<
+        &mut Text,
+        (
+            Without<FullscreenMessageText>,
+            Without<LobbyConnectedCounter>,
+        ),
+    >,
+    mut lobby_connected_counter_query: Query<
+        &mut Text,
+        (With<LobbyConnectedCounter>, Without<FullscreenMessageText>),
+    >,
 ) {
     // regularly call update_peers to update the list of connected peers
+    let mut membership_changed = false;
     for (peer, new_state) in socket.update_peers() {
+        membership_changed = true;
         // you can also handle the specific dis(connections) as they occur:
         match new_state {
             PeerState::Connected => {
-                info!("Peer {peer} connected, sending them our local RNG seed.");
+                info!("Peer {peer} connected, sending them our world vote.");
 
-                // send the local RNG seed to peer
-                let packet = rng_seeds.local.to_be_bytes().to_vec().into_boxed_slice();
-                socket.channel(1).send(packet, peer);
+                // send the local world vote to peer
+                let packet = vec![WORLD_VOTE_PACKET, world_votes.local.to_byte()];
+                socket.channel(1).send(packet.into_boxed_slice(), peer);
 
                 // reserve a spot for the peer's incoming RNG seed
                 rng_seeds.remote.insert(peer, None);
@@ -193,31 +583,186 @@ pub fn lobby_system(
             PeerState::Disconnected => {
                 info!("Peer {peer} disconnected.");
 
-                // clear the peer's RNG seed spot
+                // clear the peer's RNG seed spot, commitment and vote
                 rng_seeds.remote.remove(&peer);
+                rng_seeds.commitments.remove(&peer);
+                world_votes.remote.remove(&peer);
             }
         }
     }
 
+    // seeds revealed to a previous membership must not decide the shared seed of a new one,
+    // so every change starts a new commit-reveal round with a fresh local seed
+    if membership_changed {
+        rng_seeds.local = rand::random();
+        rng_seeds.round = lobby_round(
+            socket
+                .id()
+                .into_iter()
+                .chain(rng_seeds.remote.keys().copied()),
+        );
+        rng_seeds.remote.values_mut().for_each(|seed| *seed = None);
+        rng_seeds.revealed_to.clear();
+        info!(
+            "Starting the RNG seed round {}, sending our RNG seed commitment to all peers.",
+            rng_seeds.round
+        );
+
+        // commit to the local RNG seed, it is revealed once everyone has committed
+        // the commitment also carries the protocol version and the game settings
+        let mut packet = vec![RNG_SEED_COMMIT_PACKET, PROTOCOL_VERSION];
+        packet.extend_from_slice(
+            &config_fingerprint(&game_config, &loot_table, &matchbox_config.team_assignments)
+                .to_be_bytes(),
+        );
+        packet.extend_from_slice(&rng_seeds.round.to_be_bytes());
+        packet.extend_from_slice(&seed_commitment(rng_seeds.local));
+        for &peer in rng_seeds.remote.keys() {
+            socket
+                .channel(1)
+                .send(packet.clone().into_boxed_slice(), peer);
+        }
+    }
+
     for (peer, packet) in socket.channel(1).receive() {
         // decode the message
-        assert!(packet.len() == 8);
-        let mut remote_seed = [0; 8];
-        packet
-            .iter()
-            .enumerate()
-            .for_each(|(i, &b)| remote_seed[i] = b);
-        let remote_seed = u64::from_be_bytes(remote_seed);
-
-        if let Some(rng_seed) = rng_seeds.remote.get_mut(&peer) {
-            assert!(
-                rng_seed.is_none(),
-                "Received an RNG seed from peer {peer} twice!",
-            );
-            info!("Received an RNG seed from peer {peer}: {remote_seed}");
-            *rng_seed = Some(remote_seed);
-        } else {
-            info!("Received an RNG seed from a disconnected peer {peer}, discarding...")
+        match packet.first() {
+            Some(&RNG_SEED_COMMIT_PACKET) => {
+                let remote_version = packet.get(1).copied().unwrap_or_default();
+                if remote_version != PROTOCOL_VERSION {
+                    error!("Peer {peer} uses protocol version {remote_version}, ours is {PROTOCOL_VERSION}!");
+                    commands.remove_resource::<MatchboxSocket<MultipleChannels>>();
+                    commands.insert_resource(CurrentError(format!(
+                        "VERSION MISMATCH: expected V{PROTOCOL_VERSION} got V{remote_version}"
+                    )));
+                    app_state.set(AppState::Error);
+                    return;
+                }
+
+                let Ok(packet) = <[u8; 18 + SEED_COMMITMENT_LENGTH]>::try_from(&packet[..]) else {
+                    warn!(
+                        "Received a malformed RNG seed commitment from peer {peer}, discarding..."
+                    );
+                    continue;
+                };
+                let remote_config_fingerprint =
+                    u64::from_be_bytes(packet[2..10].try_into().unwrap());
+                let round = u64::from_be_bytes(packet[10..18].try_into().unwrap());
+                let commitment: [u8; SEED_COMMITMENT_LENGTH] = packet[18..].try_into().unwrap();
+
+                if remote_config_fingerprint
+                    != config_fingerprint(
+                        &game_config,
+                        &loot_table,
+                        &matchbox_config.team_assignments,
+                    )
+                {
+                    error!("Peer {peer} uses different game settings!");
+                    commands.remove_resource::<MatchboxSocket<MultipleChannels>>();
+                    commands.insert_resource(CurrentError("GAME SETTINGS MISMATCH".to_string()));
+                    app_state.set(AppState::Error);
+                    return;
+                }
+
+                if rng_seeds.remote.contains_key(&peer) {
+                    // the peer can get to a new round before the local player does, so only the latest commitment is kept
+                    if rng_seeds
+                        .commitments
+                        .get(&peer)
+                        .is_some_and(|(previous_round, _)| *previous_round == round)
+                    {
+                        warn!(
+                            "Received an RNG seed commitment from peer {peer} twice, discarding..."
+                        );
+                        continue;
+                    }
+                    info!("Received an RNG seed commitment from peer {peer} for round {round}");
+                    rng_seeds.commitments.insert(peer, (round, commitment));
+                } else {
+                    info!("Received an RNG seed commitment from a disconnected peer {peer}, discarding...")
+                }
+            }
+            Some(&RNG_SEED_REVEAL_PACKET) => {
+                let Ok(packet) = <[u8; 17]>::try_from(&packet[..]) else {
+                    warn!("Received a malformed RNG seed from peer {peer}, discarding...");
+                    continue;
+                };
+                let round = u64::from_be_bytes(packet[1..9].try_into().unwrap());
+                let remote_seed = u64::from_be_bytes(packet[9..].try_into().unwrap());
+
+                if !rng_seeds.remote.contains_key(&peer) {
+                    info!("Received an RNG seed from a disconnected peer {peer}, discarding...");
+                    continue;
+                }
+
+                // revealed before the peer learned about the latest membership change
+                if round != rng_seeds.round {
+                    info!(
+                        "Received an RNG seed from peer {peer} for a previous round, discarding..."
+                    );
+                    continue;
+                }
+
+                if rng_seeds.commitments.get(&peer) != Some(&(round, seed_commitment(remote_seed)))
+                {
+                    error!("The RNG seed from peer {peer} does not match their commitment!");
+                    commands.remove_resource::<MatchboxSocket<MultipleChannels>>();
+                    commands.insert_resource(CurrentError("SEED COMMITMENT MISMATCH".to_string()));
+                    app_state.set(AppState::Error);
+                    return;
+                }
+
+                let rng_seed = rng_seeds.remote.get_mut(&peer).unwrap();
+                if rng_seed.is_some() {
+                    warn!("Received an RNG seed from peer {peer} twice, discarding...");
+                    continue;
+                }
+                info!("Received an RNG seed from peer {peer}: {remote_seed}");
+                *rng_seed = Some(remote_seed);
+            }
+            Some(&WORLD_VOTE_PACKET) => {
+                let Some(world_type) = <[u8; 2]>::try_from(&packet[..])
+                    .ok()
+                    .and_then(|packet| WorldType::from_byte(packet[1]))
+                else {
+                    warn!("Received an invalid world vote from peer {peer}, discarding...");
+                    continue;
+                };
+
+                if rng_seeds.remote.contains_key(&peer) {
+                    info!("Received a world vote from peer {peer}: {world_type:?}");
+                    world_votes.remote.insert(peer, world_type);
+                } else {
+                    info!("Received a world vote from a disconnected peer {peer}, discarding...")
+                }
+            }
+            _ => warn!("Received an unknown packet from peer {peer}, discarding..."),
+        }
+    }
+
+    // reveal the local RNG seed only once the room is full and every peer has committed in this round
+    let room_full =
+        rng_seeds.remote.len() + 1 /* local player */ == matchbox_config.number_of_players as usize;
+    let all_committed = rng_seeds.remote.keys().all(|peer| {
+        rng_seeds
+            .commitments
+            .get(peer)
+            .is_some_and(|(round, _)| *round == rng_seeds.round)
+    });
+    if room_full && all_committed {
+        let unrevealed_peers = rng_seeds
+            .remote
+            .keys()
+            .filter(|peer| !rng_seeds.revealed_to.contains(*peer))
+            .copied()
+            .collect_vec();
+        for peer in unrevealed_peers {
+            info!("Revealing our local RNG seed to peer {peer}.");
+            let mut packet = vec![RNG_SEED_REVEAL_PACKET];
+            packet.extend_from_slice(&rng_seeds.round.to_be_bytes());
+            packet.extend_from_slice(&rng_seeds.local.to_be_bytes());
+            socket.channel(1).send(packet.into_boxed_slice(), peer);
+            rng_seeds.revealed_to.insert(peer);
         }
     }
 
@@ -226,10 +771,9 @@ pub fn lobby_system(
         matchbox_config.number_of_players - (1 /* local player */ + peer_rng_seeds.len() as u8);
 
     // update and recenter the info text
-    {
+    if let Ok((mut text, mut style)) = info_text_query.get_single_mut() {
         let message = format!("Waiting for {remaining} more player(s)...");
         let message_length = message.len();
-        let (mut text, mut style) = info_text_query.single_mut();
         text.sections[0].value = message;
         style.left = Val::Px(
             primary_window_query.single().width() / 2.0
@@ -237,7 +781,52 @@ pub fn lobby_system(
         );
     }
 
-    if remaining > 0 {
+    // update the portrait slots, the local player always takes the first one
+    let connected_peer_count = rng_seeds.remote.len();
+    for (&LobbyPortrait(slot), children, mut bg, mut border) in lobby_portrait_query.iter_mut() {
+        let (background_color, border_color, texture, label) = if slot <= peer_rng_seeds.len() {
+            (
+                COLORS[2],
+                COLORS[if slot == 0 { 12 } else { 0 }],
+                Some(slot),
+                "",
+            )
+        } else if slot <= connected_peer_count {
+            // connected, waiting for the avatar exchange
+            (COLORS[3], COLORS[0], None, "...")
+        } else {
+            (COLORS[8], COLORS[0], None, "?")
+        };
+
+        *bg = background_color.into();
+        *border = Color::from(border_color).into();
+        for &child in children.iter() {
+            if let Ok((mut image, mut visibility)) = lobby_portrait_image_query.get_mut(child) {
+                match texture {
+                    Some(slot) => {
+                        image.texture = game_textures
+                            .get_player_texture(PlayerID(slot as u8))
+                            .clone();
+                        *visibility = Visibility::Inherited;
+                    }
+                    None => *visibility = Visibility::Hidden,
+                }
+            }
+            if let Ok(mut text) = lobby_portrait_text_query.get_mut(child) {
+                text.sections[0].value = label.to_string();
+            }
+        }
+    }
+
+    if let Ok(mut text) = lobby_connected_counter_query.get_single_mut() {
+        text.sections[0].value = format!(
+            "{}/{} connected",
+            1 /* local player */ + connected_peer_count,
+            matchbox_config.number_of_players
+        );
+    }
+
+    if remaining > 0 || world_votes.remote.len() < peer_rng_seeds.len() {
         return;
     }
 
@@ -245,7 +834,27 @@ pub fn lobby_system(
         rng_seeds.local ^ peer_rng_seeds.into_iter().reduce(|acc, e| acc ^ e).unwrap();
     info!("Generated the shared RNG seed: {shared_seed}");
     commands.remove_resource::<RngSeeds>();
-    commands.insert_resource(SessionRng::new(shared_seed));
+    let mut session_rng = SessionRng::new(shared_seed);
+
+    // the world with the most votes wins, ties are broken randomly
+    let vote_counts = WorldType::LIST.map(|w| {
+        world_votes.remote.values().filter(|&&v| v == w).count()
+            + usize::from(world_votes.local == w)
+    });
+    let max_vote_count = *vote_counts.iter().max().unwrap();
+    let tied_world_types = WorldType::LIST
+        .into_iter()
+        .zip(vote_counts)
+        .filter(|(_, count)| *count == max_vote_count)
+        .map(|(w, _)| w)
+        .collect_vec();
+    let voted_world_type =
+        tied_world_types[session_rng.gen_range(0, tied_world_types.len() as u64) as usize];
+    info!("The starting world was voted to be: {voted_world_type:?}");
+    matchbox_config.voted_world_type = Some(voted_world_type);
+    commands.remove_resource::<WorldVotes>();
+
+    commands.insert_resource(session_rng);
 
     // extract final player list
     let players = socket.players();
@@ -254,7 +863,10 @@ pub fn lobby_system(
         .with_max_prediction_window(MAX_PREDICTED_FRAMES as usize)
         .unwrap()
         .with_num_players(matchbox_config.number_of_players as usize)
-        .with_desync_detection_mode(bevy_ggrs::ggrs::DesyncDetection::On { interval: 1 });
+        .with_desync_detection_mode(match matchbox_config.desync_detection_interval {
+            None => DesyncDetection::Off,
+            Some(interval) => DesyncDetection::On { interval },
+        });
 
     let mut local_player_id = None;
     for (i, player) in players.into_iter().enumerate() {
@@ -268,9 +880,30 @@ pub fn lobby_system(
             local_player_id = Some(LocalPlayerID(i as u8));
         }
     }
-    commands.insert_resource(local_player_id.unwrap());
+    let local_player_id = local_player_id.unwrap();
+    let input_delay = matchbox_config
+        .player_input_delays
+        .get(local_player_id.0 as usize)
+        .copied()
+        .unwrap_or(0);
+    info!("Local input delay: {input_delay}");
+    sess_build = sess_build.with_input_delay(input_delay);
+    commands.insert_resource(local_player_id);
 
     let channel = socket.take_channel(0).unwrap();
+    #[cfg(debug_assertions)]
+    let channel = {
+        if matchbox_config.simulated_packet_loss > 0 {
+            warn!(
+                "Simulating {}% packet loss",
+                matchbox_config.simulated_packet_loss
+            );
+        }
+        LossyChannel {
+            channel,
+            loss_percentage: matchbox_config.simulated_packet_loss,
+        }
+    };
 
     let sess = sess_build
         .start_p2p_session(channel)
@@ -293,18 +926,27 @@ pub fn teardown_lobby(
 pub fn handle_ggrs_events(
     mut session: ResMut<Session<GgrsConfig>>,
     mut commands: Commands,
-    fonts: Res<Fonts>,
-    primary_window_query: Query<&Window, With<PrimaryWindow>>,
     teardown_entities_query: Query<Entity, (Without<Window>, Without<Camera2d>)>,
+    mut rollback_debug_state: ResMut<RollbackDebugState>,
     mut app_state: ResMut<NextState<AppState>>,
 ) {
+    // only shown while GGRS keeps recommending to wait
+    rollback_debug_state.frames_behind = 0;
+
     match session.as_mut() {
         Session::P2P(s) => {
             for event in s.events() {
                 info!("GgrsEvent: {event:?}");
                 let error_message = match event {
                     bevy_ggrs::ggrs::GgrsEvent::Disconnected { .. } => Some("DISCONNECTED!"),
-                    bevy_ggrs::ggrs::GgrsEvent::DesyncDetected { .. } => Some("DESYNCED!"),
+                    bevy_ggrs::ggrs::GgrsEvent::DesyncDetected { .. } => {
+                        rollback_debug_state.desync_status = DesyncStatus::Desynced;
+                        Some("DESYNCED!")
+                    }
+                    bevy_ggrs::ggrs::GgrsEvent::WaitRecommendation { skip_frames } => {
+                        rollback_debug_state.frames_behind = skip_frames;
+                        None
+                    }
                     _ => None,
                 };
 
@@ -314,21 +956,355 @@ pub fn handle_ggrs_events(
                     teardown_entities_query
                         .iter()
                         .for_each(|e| commands.entity(e).despawn());
-                    setup_fullscreen_message_display(
-                        &mut commands,
-                        primary_window_query.single(),
-                        &fonts,
-                        error_message,
-                    );
+                    commands.insert_resource(CurrentError(error_message.to_string()));
                     app_state.set(AppState::Error);
                     return;
                 }
             }
         }
+        // the training mode sync test session doesn't resimulate frames, so there are no checksums to compare
+        Session::SyncTest(_) => (),
         _ => unreachable!(),
     }
 }
 
+// runs last in the rollback schedule, so a resimulated frame is compared with what was predicted for it
+pub fn record_ghost_positions(
+    mut ghost_position_state: ResMut<GhostPositionState>,
+    player_query: Query<(&Player, &Position), Without<Dead>>,
+    frame_count: Res<FrameCount>,
+) {
+    let positions: HashMap<PlayerID, Position> = player_query
+        .iter()
+        .map(|(player, &position)| (player.id, position))
+        .collect();
+
+    if let Some(predicted_positions) = ghost_position_state
+        .positions
+        .insert(frame_count.frame, positions.clone())
+    {
+        // later frames overwrite earlier ones, leaving the correction of the last frame that was shown
+        for (player_id, predicted_position) in predicted_positions {
+            match positions.get(&player_id) {
+                Some(&position) if position != predicted_position => {
+                    ghost_position_state
+                        .corrections
+                        .insert(player_id, predicted_position);
+                }
+                _ => {
+                    ghost_position_state.corrections.remove(&player_id);
+                }
+            }
+        }
+    }
+
+    // older frames are confirmed and never resimulated
+    let oldest_frame = frame_count.frame.saturating_sub(MAX_PREDICTED_FRAMES);
+    ghost_position_state
+        .positions
+        .retain(|&frame, _| frame >= oldest_frame);
+}
+
+pub fn ghost_position_system(
+    mut commands: Commands,
+    game_textures: Res<GameTextures>,
+    rendered_frame_count: Res<bevy::core::FrameCount>,
+    mut ghost_position_state: ResMut<GhostPositionState>,
+) {
+    for (player_id, position) in ghost_position_state.corrections.drain() {
+        commands.spawn((
+            SpriteBundle {
+                texture: game_textures.get_player_texture(player_id).clone(),
+                transform: Transform::from_xyz(
+                    get_x(position.x),
+                    get_y(position.y),
+                    PLAYER_Z_LAYER - 1.0,
+                ),
+                sprite: Sprite {
+                    color: Color::WHITE.with_a(0.4),
+                    custom_size: Some(Vec2::new(TILE_WIDTH as f32, TILE_HEIGHT as f32)),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            RollbackGhost {
+                expiration_frame: rendered_frame_count.0 + GHOST_DISPLAY_FRAMES,
+            },
+        ));
+    }
+}
+
+pub fn bomb_ghost_system(
+    mut commands: Commands,
+    game_textures: Res<GameTextures>,
+    player_query: Query<(&Player, &Position, &HeldAction), Without<Dead>>,
+    mut bomb_ghost_query: Query<(Entity, &BombGhost, &mut Transform)>,
+) {
+    let held_positions: HashMap<PlayerID, Position> = player_query
+        .iter()
+        .filter(|(_, _, held_action)| held_action.frames_held < BOMB_HOLD_FRAMES)
+        .map(|(player, position, _)| (player.id, *position))
+        .collect();
+
+    let mut ghosted_players = HashSet::new();
+    for (entity, bomb_ghost, mut transform) in bomb_ghost_query.iter_mut() {
+        if let Some(position) = held_positions.get(&bomb_ghost.0) {
+            transform.translation.x = get_x(position.x);
+            transform.translation.y = get_y(position.y);
+            ghosted_players.insert(bomb_ghost.0);
+        } else {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+
+    for (player_id, position) in held_positions
+        .into_iter()
+        .filter(|(player_id, _)| !ghosted_players.contains(player_id))
+    {
+        commands.spawn((
+            SpriteBundle {
+                texture: game_textures.bomb.clone(),
+                transform: Transform::from_xyz(get_x(position.x), get_y(position.y), BOMB_Z_LAYER),
+                sprite: Sprite {
+                    color: Color::WHITE.with_a(0.4),
+                    custom_size: Some(Vec2::new(TILE_WIDTH as f32, TILE_HEIGHT as f32)),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            BombGhost(player_id),
+        ));
+    }
+}
+
+pub fn ghost_cleanup(
+    mut commands: Commands,
+    rendered_frame_count: Res<bevy::core::FrameCount>,
+    ghost_query: Query<(Entity, &RollbackGhost)>,
+) {
+    for (entity, ghost) in ghost_query.iter() {
+        if rendered_frame_count.0 >= ghost.expiration_frame {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+pub fn rollback_debug_display(
+    mut commands: Commands,
+    time: Res<Time>,
+    keyboard_input: Res<Input<KeyCode>>,
+    fonts: Res<Fonts>,
+    session: Option<Res<Session<GgrsConfig>>>,
+    frame_count: Res<FrameCount>,
+    matchbox_config: Res<MatchboxConfig>,
+    mut settings: ResMut<SettingsResource>,
+    mut rollback_debug_state: ResMut<RollbackDebugState>,
+    mut elapsed_secs: Local<f32>,
+    mut display_query: Query<(Entity, &mut Text), With<RollbackDebugDisplay>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::F3) {
+        settings.show_rollback_debug = !settings.show_rollback_debug;
+    }
+
+    *elapsed_secs += time.delta_seconds();
+    if *elapsed_secs >= 1.0 {
+        *elapsed_secs = 0.0;
+        rollback_debug_state.rollbacks_this_second =
+            std::mem::take(&mut rollback_debug_state.pending_rollbacks);
+    }
+
+    if let (Some(session @ Session::P2P(_)), Some(desync_detection_interval)) = (
+        session.as_deref(),
+        matchbox_config.desync_detection_interval,
+    ) {
+        if rollback_debug_state.desync_status != DesyncStatus::Desynced {
+            // checksums are only compared for confirmed frames, pending while those fall behind the interval
+            let unconfirmed_frames = confirmed_frame(session, &frame_count)
+                .map_or(frame_count.frame, |confirmed_frame| {
+                    frame_count.frame.saturating_sub(confirmed_frame)
+                });
+            rollback_debug_state.desync_status = if unconfirmed_frames > desync_detection_interval {
+                DesyncStatus::Checking
+            } else {
+                DesyncStatus::Ok
+            };
+        }
+    }
+
+    if !settings.show_rollback_debug {
+        for (entity, _) in display_query.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+        return;
+    }
+
+    let value = format!(
+        "Rollbacks/s: {} | Behind: {} frames | {}",
+        rollback_debug_state.rollbacks_this_second,
+        rollback_debug_state.frames_behind,
+        match rollback_debug_state.desync_status {
+            DesyncStatus::Ok => "OK",
+            DesyncStatus::Checking => "CHECKING",
+            DesyncStatus::Desynced => "DESYNCED",
+        }
+    );
+    if let Ok((_, mut text)) = display_query.get_single_mut() {
+        text.sections[0].value = value;
+    } else {
+        commands.spawn((
+            TextBundle {
+                text: Text::from_section(
+                    value,
+                    TextStyle {
+                        font: fonts.mono.clone(),
+                        font_size: 2.0 * PIXEL_SCALE as f32,
+                        color: COLORS[15].into(),
+                    },
+                ),
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    bottom: Val::Px(0.0),
+                    left: Val::Px(0.0),
+                    ..Default::default()
+                },
+                z_index: ZIndex::Global(3),
+                ..Default::default()
+            },
+            RollbackDebugDisplay,
+        ));
+    }
+}
+
+pub fn setup_error_screen(
+    mut commands: Commands,
+    current_error: Res<CurrentError>,
+    fonts: Res<Fonts>,
+    rendered_frame_count: Res<bevy::core::FrameCount>,
+    camera_query: Query<(), With<Camera2d>>,
+    primary_window_query: Query<&Window, With<PrimaryWindow>>,
+) {
+    // the error might have happened before the lobby camera was spawned
+    if camera_query.is_empty() {
+        commands.spawn(Camera2dBundle::default());
+    }
+
+    let window = primary_window_query.single();
+    setup_fullscreen_message_display(
+        &mut commands,
+        window,
+        &fonts,
+        &current_error.0,
+        rendered_frame_count.0,
+    );
+    setup_retry_button(&mut commands, window, &fonts);
+}
+
+pub fn error_screen_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    retry_button_query: Query<&Interaction, (Changed<Interaction>, With<RetryButton>)>,
+    mut app_state: ResMut<NextState<AppState>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::R)
+        || retry_button_query
+            .iter()
+            .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        info!("Retrying...");
+        app_state.set(AppState::Lobby);
+    }
+}
+
+pub fn setup_auto_retry(
+    mut commands: Commands,
+    auto_retry: Option<ResMut<AutoRetry>>,
+    fonts: Res<Fonts>,
+    primary_window_query: Query<&Window, With<PrimaryWindow>>,
+) {
+    let now = Instant::now();
+    let attempts_remaining = match auto_retry {
+        Some(mut auto_retry) => {
+            auto_retry.next_retry_instant = now + Duration::from_secs(auto_retry.backoff_secs);
+            auto_retry.attempts_remaining
+        }
+        None => {
+            commands.insert_resource(AutoRetry {
+                attempts_remaining: MAX_AUTO_RETRIES,
+                next_retry_instant: now + Duration::from_secs(INITIAL_AUTO_RETRY_BACKOFF_SECS),
+                backoff_secs: INITIAL_AUTO_RETRY_BACKOFF_SECS,
+            });
+            MAX_AUTO_RETRIES
+        }
+    };
+
+    if attempts_remaining > 0 {
+        let window = primary_window_query.single();
+        commands.spawn((
+            TextBundle {
+                text: Text::from_section(
+                    "",
+                    TextStyle {
+                        font: fonts.mono.clone(),
+                        font_size: 1.5 * PIXEL_SCALE as f32,
+                        color: COLORS[7].into(),
+                    },
+                ),
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(window.height() / 2.0 + (12 * PIXEL_SCALE) as f32),
+                    left: Val::Px(window.width() / 2.0 - (6 * PIXEL_SCALE) as f32),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            AutoRetryCountdown,
+        ));
+    }
+}
+
+pub fn auto_retry_system(
+    mut auto_retry: ResMut<AutoRetry>,
+    mut countdown_text_query: Query<&mut Text, With<AutoRetryCountdown>>,
+    mut app_state: ResMut<NextState<AppState>>,
+) {
+    // leave the error screen static once out of attempts
+    if auto_retry.attempts_remaining == 0 {
+        return;
+    }
+
+    let now = Instant::now();
+    if now >= auto_retry.next_retry_instant {
+        auto_retry.attempts_remaining -= 1;
+        auto_retry.backoff_secs *= 2;
+        info!(
+            "Automatically retrying, {} attempt(s) remaining...",
+            auto_retry.attempts_remaining
+        );
+        app_state.set(AppState::Lobby);
+        return;
+    }
+
+    if let Ok(mut text) = countdown_text_query.get_single_mut() {
+        let remaining_secs = auto_retry
+            .next_retry_instant
+            .saturating_duration_since(now)
+            .as_secs_f32()
+            .ceil();
+        text.sections[0].value = format!("Retrying in {remaining_secs}s…");
+    }
+}
+
+pub fn teardown_error_screen(
+    mut commands: Commands,
+    // the lobby spawns a new camera
+    teardown_entities_query: Query<Entity, Without<Window>>,
+) {
+    teardown_entities_query
+        .iter()
+        .for_each(|e| commands.entity(e).despawn());
+    commands.remove_resource::<CurrentError>();
+    commands.insert_resource(FrameCount { frame: 0 });
+}
+
 pub fn setup_game(
     mut commands: Commands,
     mut session_rng: ResMut<SessionRng>,
@@ -338,19 +1314,52 @@ pub fn setup_game(
     game_textures: Res<GameTextures>,
     fonts: Res<Fonts>,
     local_player_id: Res<LocalPlayerID>,
+    game_config: Res<GameConfig>,
+    mut session_timer: ResMut<SessionTimer>,
+    mut game_integrity_violation: ResMut<GameIntegrityViolation>,
 ) {
+    // the game started so the next error gets a fresh set of retries
+    commands.remove_resource::<AutoRetry>();
+    commands.insert_resource(RollbackDebugState::default());
+    commands.insert_resource(GameStats::default());
+    commands.insert_resource(GhostPositionState::default());
+    *session_timer = SessionTimer::default();
+    commands.remove_resource::<SessionTimeUp>();
+    // frames of the new session start over
+    game_integrity_violation.pending.clear();
+
     // choose the initial world
-    let world_type = WorldType::random(&mut session_rng);
+    let world_type = matchbox_config
+        .voted_world_type
+        .unwrap_or_else(|| WorldType::random(&mut session_rng));
     commands.insert_resource(world_type);
+    commands.insert_resource(CurrentRoundModifier(RoundModifier::Normal));
 
     // setup the tournament leaderboard
     commands.insert_resource(Leaderboard {
         scores: (0..matchbox_config.number_of_players)
             .map(|p| (PlayerID(p), 0))
             .collect(),
-        winning_score: 3,
+        tournament_format: game_config.tournament_format,
+        score_mode: game_config.score_mode,
+        rounds_played: 0,
+        tournament_start_scores: HashMap::new(),
+        // the assignments were validated against the number of players when parsed
+        teams: match &matchbox_config.team_assignments {
+            Some(team_assignments) => team_assignments
+                .iter()
+                .enumerate()
+                .map(|(p, &team)| (PlayerID(p as u8), team))
+                .collect(),
+            None => HashMap::new(),
+        },
     });
 
+    // choose a tip to show while waiting for the game to start
+    let tip = CurrentTip(
+        GAMEPLAY_TIPS[session_rng.gen_range(0, GAMEPLAY_TIPS.len() as u64) as usize].to_string(),
+    );
+
     // setup the "get ready" display
     setup_get_ready_display(
         &mut commands,
@@ -359,7 +1368,9 @@ pub fn setup_game(
         &fonts,
         matchbox_config.number_of_players,
         local_player_id.0,
+        &tip,
     );
+    commands.insert_resource(tip);
     commands.remove_resource::<LocalPlayerID>();
 
     commands.insert_resource(GameFreeze {
@@ -368,8 +1379,46 @@ pub fn setup_game(
     });
 }
 
-pub fn increase_frame_system(mut frame_count: ResMut<FrameCount>) {
+pub fn increase_frame_system(
+    mut commands: Commands,
+    mut frame_count: ResMut<FrameCount>,
+    mut rollback_debug_state: ResMut<RollbackDebugState>,
+    mut session_timer: ResMut<SessionTimer>,
+    game_config: Res<GameConfig>,
+) {
     frame_count.frame += 1;
+    session_timer.total_frames += 1;
+
+    if game_config.max_session_frames == Some(session_timer.total_frames) {
+        info!(
+            "[frame:{}] The session time limit was reached.",
+            frame_count.frame
+        );
+        commands.insert_resource(SessionTimeUp);
+    }
+
+    // a frame that was already simulated is simulated again only after a rollback
+    if frame_count.frame <= rollback_debug_state.last_simulated_frame {
+        rollback_debug_state.pending_rollbacks += 1;
+        rollback_debug_state.last_rollback_frame = frame_count.frame;
+    }
+    rollback_debug_state.last_simulated_frame = frame_count.frame;
+}
+
+// events of this and later frames are left over from before a rollback
+pub fn clear_stale_game_events(
+    game_event_queue: Option<ResMut<GameEventQueue>>,
+    mut wall_of_death_done_events: ResMut<Events<WallOfDeathDone>>,
+    frame_count: Res<FrameCount>,
+) {
+    // only read within the frame that sent it, so a resimulated frame sends it again without duplicates
+    wall_of_death_done_events.clear();
+
+    if let Some(mut game_event_queue) = game_event_queue {
+        game_event_queue
+            .0
+            .retain(|event| event.frame < frame_count.frame);
+    }
 }
 
 pub fn update_hud_clock(
@@ -388,6 +1437,38 @@ pub fn update_hud_clock(
     clock_text_query.single_mut().sections[0].value = format_hud_time(remaining_seconds);
 }
 
+pub fn update_wall_count(
+    wall_query: Query<(), (With<Wall>, With<Destructible>, Without<Crumbling>)>,
+    mut wall_count_text_query: Query<&mut Text, With<WallCountDisplay>>,
+) {
+    let wall_count = wall_query.iter().count();
+    let mut text = wall_count_text_query.single_mut();
+    text.sections[0].value = format!("Walls: {wall_count}");
+    text.sections[0].style.color = match wall_count {
+        0..=1 => COLORS[12],
+        2..=4 => COLORS[14],
+        _ => COLORS[15],
+    }
+    .into();
+}
+
+pub fn update_player_health_bars(
+    player_query: Query<(&Player, &PlayerHP), Without<Dead>>,
+    mut health_bar_query: Query<(&mut Style, &PlayerHealthBar)>,
+) {
+    let player_hps: HashMap<PlayerID, &PlayerHP> = player_query
+        .iter()
+        .map(|(player, player_hp)| (player.id, player_hp))
+        .collect();
+
+    for (mut style, health_bar) in health_bar_query.iter_mut() {
+        let fraction = player_hps.get(&health_bar.0).map_or(0.0, |player_hp| {
+            player_hp.current as f32 / player_hp.max as f32
+        });
+        style.width = Val::Percent(100.0 * fraction);
+    }
+}
+
 pub fn update_player_portraits(
     player_query: Query<&Player>,
     mut portrait_visibility_query: Query<(&mut Visibility, &PlayerPortrait)>,
@@ -403,6 +1484,24 @@ pub fn update_player_portraits(
     }
 }
 
+pub fn sync_map_system(
+    mut map: ResMut<Map>,
+    solid_object_query: Query<(Entity, &Position), With<Solid>>,
+) {
+    // clearing keeps the allocated capacity around for the next frame
+    map.solids.clear();
+    for (entity, &position) in solid_object_query.iter() {
+        let previous_entity = map.solids.insert(position, entity);
+
+        // there must only be one solid per position
+        // if there are multiple bombs on the same position only one would get updated which could lead to a desync
+        assert!(
+            previous_entity.is_none(),
+            "Multiple solid objects on position {position:?}!"
+        );
+    }
+}
+
 pub fn player_move(
     mut session_rng: ResMut<SessionRng>,
     mut commands: Commands,
@@ -411,14 +1510,22 @@ pub fn player_move(
     mut alive_player_query: Query<
         (
             &Rollback,
-            &Player,
+            &mut Player,
             &mut Position,
             &mut Transform,
             &mut Sprite,
+            Entity,
+            Option<&Slowed>,
         ),
         (Without<Dead>, Without<Solid>),
     >,
-    solid_object_query: Query<(Entity, &Position, Option<&Bomb>), With<Solid>>,
+    map: Res<Map>,
+    bomb_query: Query<Entity, With<Bomb>>,
+    water_tile_query: Query<&Position, (With<WaterTile>, Without<Player>)>,
+    mut crackable_ice_query: Query<(&Position, &mut CrackableIce), Without<Player>>,
+    current_round_modifier: Res<CurrentRoundModifier>,
+    mut game_stats: ResMut<GameStats>,
+    mut game_event_queue: Option<ResMut<GameEventQueue>>,
     frame_count: Res<FrameCount>,
     game_freeze: Option<Res<GameFreeze>>,
 ) {
@@ -426,23 +1533,7 @@ pub fn player_move(
         return;
     }
 
-    let mut solids = HashMap::new();
-    for (p, b) in solid_object_query
-        .iter()
-        .map(|(solid_entity, solid_position, optional_bomb)| {
-            (*solid_position, optional_bomb.map(|_| solid_entity))
-        })
-    {
-        let previous_item = solids.insert(p, b);
-
-        // there must only be one solid per position
-        // if there are multiple bombs on the same position only one would get updated which could lead to a desync
-        assert!(
-            previous_item.is_none(),
-            "Multiple solid objects on position {p:?}!"
-        );
-    }
-    let solids = solids;
+    let water_tile_positions: HashSet<Position> = water_tile_query.iter().copied().collect();
 
     // player sorting is needed to ensure determinism of pushing bombs
     let mut players = alive_player_query
@@ -451,47 +1542,193 @@ pub fn player_move(
         .collect_vec();
     // shuffle to ensure fairness in situations where two players push the same bomb in the same frame
     shuffle(&mut players, &mut session_rng);
-    for (_, player, mut position, mut transform, mut sprite) in players {
+    for (_, mut player, mut position, mut transform, mut sprite, player_entity, slowed) in players {
+        let slowed = match slowed {
+            Some(slowed) if frame_count.frame >= slowed.expires_frame => {
+                commands.entity(player_entity).remove::<Slowed>();
+                None
+            }
+            slowed => slowed,
+        };
+
+        // presses made while the player can't move yet are kept for the next allowed frame
         let input = inputs[player.id.0 as usize].0 .0;
-        for (input_mask, moving_direction) in [
+        if let Some((_, direction)) = [
             (INPUT_UP, Direction::Up),
             (INPUT_DOWN, Direction::Down),
             (INPUT_LEFT, Direction::Left),
             (INPUT_RIGHT, Direction::Right),
-        ] {
-            if input & input_mask != 0 {
-                info!(
-                    "[frame:{}] Player {} moved in direction {moving_direction:?} at position: {position:?}",
-                    frame_count.frame, player.id.0,
-                );
+        ]
+        .into_iter()
+        .find(|(input_mask, _)| input & input_mask != 0)
+        {
+            player.pending_move = Some(direction);
+        }
 
-                // visual / sprite flipping
-                match moving_direction {
-                    Direction::Left => sprite.flip_x = true,
-                    Direction::Right => sprite.flip_x = false,
-                    _ => (),
-                }
+        if player.is_stunned(frame_count.frame) {
+            player.pending_move = None;
+            continue;
+        }
+        if !player.can_move(frame_count.frame) {
+            continue;
+        }
+
+        let Some(moving_direction) = player.pending_move.take() else {
+            continue;
+        };
+        let move_frame_interval = match current_round_modifier.0 {
+            RoundModifier::SpeedRound => (player.move_cooldown_frames
+                / SPEED_ROUND_MOVE_INTERVAL_DIVISOR)
+                .max(MIN_PLAYER_MOVE_FRAME_INTERVAL),
+            _ => player.move_cooldown_frames,
+        };
+        player.next_move_frame = frame_count.frame
+            + move_frame_interval * slowed.map_or(1, |slowed| slowed.move_frame_delay);
 
-                let new_position = position.offset(moving_direction, 1);
-                let solid = solids.get(&new_position);
+        info!(
+            "[frame:{}] Player {} moved in direction {moving_direction:?} at position: {position:?}",
+            frame_count.frame, player.id.0,
+        );
 
-                if let Some(&optional_bomb_entity) = solid {
-                    if player.can_push_bombs {
-                        if let Some(bomb_entity) = optional_bomb_entity {
-                            commands.entity(bomb_entity).insert(Moving {
-                                direction: moving_direction,
-                                next_move_frame: frame_count.frame,
-                                frame_interval: MOVING_OBJECT_FRAME_INTERVAL,
-                            });
-                        }
-                    }
-                } else {
-                    *position = new_position;
-                    let translation = &mut transform.translation;
-                    translation.x = get_x(position.x);
-                    translation.y = get_y(position.y);
+        // visual / sprite flipping
+        match moving_direction {
+            Direction::Left => sprite.flip_x = true,
+            Direction::Right => sprite.flip_x = false,
+            _ => (),
+        }
+
+        let Some(new_position) = position.checked_offset(moving_direction, 1) else {
+            // blocked by the edge of the map
+            continue;
+        };
+        if let Some(&solid_entity) = map.solids.get(&new_position) {
+            if player.can_push_bombs {
+                if let Ok(bomb_entity) = bomb_query.get(solid_entity) {
+                    commands.entity(bomb_entity).insert(Moving {
+                        direction: moving_direction,
+                        next_move_frame: frame_count.frame,
+                        frame_interval: MOVING_OBJECT_FRAME_INTERVAL,
+                    });
                 }
             }
+        } else {
+            *position = new_position;
+            let translation = &mut transform.translation;
+            translation.x = get_x(position.x);
+            translation.y = get_y(position.y);
+            player.last_move_direction = Some(moving_direction);
+            if let Some((_, mut crackable_ice)) = crackable_ice_query
+                .iter_mut()
+                .find(|(&ice_position, _)| ice_position == *position)
+            {
+                crackable_ice.hp = crackable_ice.hp.saturating_sub(1);
+            }
+            if water_tile_positions.contains(&position) {
+                commands.entity(player_entity).insert(Slowed {
+                    move_frame_delay: SLOWED_MOVE_FRAME_DELAY,
+                    expires_frame: frame_count.frame + SLOW_DURATION_FRAMES,
+                });
+            }
+
+            *game_stats.tiles_walked.entry(player.id).or_insert(0) += 1;
+            if let Some(game_event_queue) = game_event_queue.as_mut() {
+                game_event_queue.push(
+                    frame_count.frame,
+                    GameEventKind::PlayerMoved {
+                        player_id: player.id.0,
+                        y: position.y,
+                        x: position.x,
+                    },
+                );
+            }
+        }
+    }
+}
+
+pub fn item_magnetize(
+    mut session_rng: ResMut<SessionRng>,
+    rollback_ordered: Res<RollbackOrdered>,
+    alive_player_query: Query<(&Player, &Position), (Without<Dead>, Without<Item>)>,
+    mut item_query: Query<(&Rollback, &mut Position, &mut Transform), With<Item>>,
+    impassable_object_query: Query<
+        &Position,
+        (Without<Item>, Or<(With<Solid>, With<BurningItem>)>),
+    >,
+    game_freeze: Option<Res<GameFreeze>>,
+) {
+    if game_freeze.is_some() {
+        return;
+    }
+
+    let magnets = alive_player_query
+        .iter()
+        .filter(|(player, _)| player.magnet_radius > 0)
+        .sorted_by_key(|(player, _)| player.id.0)
+        .map(|(player, &position)| (position, player.magnet_radius))
+        .collect_vec();
+    if magnets.is_empty() {
+        return;
+    }
+
+    let mut occupied_positions: HashSet<Position> = impassable_object_query
+        .iter()
+        .copied()
+        .chain(item_query.iter().map(|(_, &position, _)| position))
+        .collect();
+
+    // item sorting is needed to ensure movement determinism
+    let mut items = item_query
+        .iter_mut()
+        .sorted_by_cached_key(|q| rollback_ordered.order(*q.0))
+        .collect_vec();
+    // shuffle to ensure fairness in situations where two items compete for the same position
+    shuffle(&mut items, &mut session_rng);
+    for (_, mut position, mut transform) in items {
+        // pull towards the closest magnet in range
+        if let Some(&(magnet_position, _)) = magnets
+            .iter()
+            .filter(|(magnet_position, magnet_radius)| {
+                magnet_position.manhattan_distance(&position) <= *magnet_radius as u32
+            })
+            .min_by_key(|(magnet_position, _)| magnet_position.manhattan_distance(&position))
+        {
+            if magnet_position == *position {
+                continue;
+            }
+
+            let vertical_direction = match magnet_position.y.cmp(&position.y) {
+                std::cmp::Ordering::Less => Some(Direction::Up),
+                std::cmp::Ordering::Greater => Some(Direction::Down),
+                std::cmp::Ordering::Equal => None,
+            };
+            let horizontal_direction = match magnet_position.x.cmp(&position.x) {
+                std::cmp::Ordering::Less => Some(Direction::Left),
+                std::cmp::Ordering::Greater => Some(Direction::Right),
+                std::cmp::Ordering::Equal => None,
+            };
+            // prefer closing the larger gap first
+            let directions = if magnet_position.y.abs_diff(position.y)
+                >= magnet_position.x.abs_diff(position.x)
+            {
+                [vertical_direction, horizontal_direction]
+            } else {
+                [horizontal_direction, vertical_direction]
+            };
+
+            if let Some(next_position) = directions
+                .into_iter()
+                .flatten()
+                .filter_map(|direction| position.checked_offset(direction, 1))
+                .find(|next_position| !occupied_positions.contains(next_position))
+            {
+                occupied_positions.remove(&*position);
+                occupied_positions.insert(next_position);
+
+                *position = next_position;
+                let translation = &mut transform.translation;
+                translation.x = get_x(position.x);
+                translation.y = get_y(position.y);
+            }
         }
     }
 }
@@ -512,6 +1749,7 @@ pub fn bomb_move(
         >,
         Query<&Position, (Without<Moving>, Or<(With<Solid>, With<Item>, With<Player>)>)>,
     )>,
+    map_size: Res<MapSize>,
     frame_count: Res<FrameCount>,
     game_freeze: Option<Res<GameFreeze>>,
 ) {
@@ -550,7 +1788,16 @@ pub fn bomb_move(
         .sorted_by_cached_key(|q| rollback_ordered.order(*q.0))
         .collect_vec();
 
+    // every iteration should move at least one bomb, so this is never reached unless the loop logic breaks
+    let max_iterations = map_size.rows as usize * map_size.columns as usize;
+    let mut iteration = 0;
     loop {
+        if iteration >= max_iterations {
+            warn!("bomb_move infinite loop prevented after {max_iterations} iterations");
+            break;
+        }
+        iteration += 1;
+
         let moving_bombs = moving_bombs_left_to_check;
         moving_bombs_left_to_check = vec![];
         let mut bombs_moved = false;
@@ -558,13 +1805,18 @@ pub fn bomb_move(
         for moving_bomb in moving_bombs {
             let moving_bomb_entity = moving_bomb.1;
             let current_position = *moving_bomb.3;
-            let next_position = current_position.offset(moving_bomb.2.direction, 1);
-            if static_impassable_object_positions.contains(&next_position) {
-                // hit an impassable object, stop moving the bomb
+            let next_position = current_position
+                .checked_offset(moving_bomb.2.direction, 1)
+                .filter(|p| !static_impassable_object_positions.contains(p));
+            let Some(next_position) = next_position else {
+                // hit an impassable object or the edge of the map, stop moving the bomb
                 commands.entity(moving_bomb_entity).remove::<Moving>();
                 positions_of_bombs_ready_to_move.remove(&current_position);
                 static_impassable_object_positions.insert(current_position);
-            } else if positions_of_bombs_ready_to_move.contains(&next_position) {
+                continue;
+            };
+
+            if positions_of_bombs_ready_to_move.contains(&next_position) {
                 // a bomb that is about to move is blocking the way, check again later
                 moving_bombs_left_to_check.push(moving_bomb);
             } else {
@@ -593,10 +1845,16 @@ pub fn bomb_move(
 }
 
 pub fn pick_up_item(
+    mut session_rng: ResMut<SessionRng>,
     mut commands: Commands,
+    rollback_ordered: Res<RollbackOrdered>,
     game_textures: Res<GameTextures>,
+    world_type: Res<WorldType>,
+    world_configs: Res<WorldConfigs>,
     mut alive_player_query: Query<(&mut Player, &Position, &mut BombSatchel), Without<Dead>>,
-    mut item_query: Query<(Entity, &Item, &Position, &mut Handle<Image>)>,
+    mut item_query: Query<(&Rollback, Entity, &Item, &Position, &mut Handle<Image>)>,
+    mut game_stats: ResMut<GameStats>,
+    mut game_event_queue: Option<ResMut<GameEventQueue>>,
     frame_count: Res<FrameCount>,
     game_freeze: Option<Res<GameFreeze>>,
 ) {
@@ -604,7 +1862,11 @@ pub fn pick_up_item(
         return;
     }
 
-    for (item_entity, &item, &item_position, mut item_texture) in item_query.iter_mut() {
+    // item sorting is needed to ensure determinism of the skull effect rolls
+    for (_, item_entity, &item, &item_position, mut item_texture) in item_query
+        .iter_mut()
+        .sorted_by_cached_key(|q| rollback_ordered.order(*q.0))
+    {
         let mut players_at_item_position =
             alive_player_query
                 .iter_mut()
@@ -629,7 +1891,58 @@ pub fn pick_up_item(
                     Item::BombPush => {
                         player.can_push_bombs = true;
                     }
+                    Item::SpeedUp => {
+                        player.move_cooldown_frames = player
+                            .move_cooldown_frames
+                            .saturating_sub(1)
+                            .max(MIN_PLAYER_MOVE_FRAME_INTERVAL);
+                    }
+                    Item::BombDown => {
+                        // only the bombs in the satchel are counted, so a player with every bomb
+                        // on the field doesn't get one back
+                        if bomb_satchel.bombs_available > 1 {
+                            bomb_satchel.bombs_available -= 1;
+                        }
+                    }
+                    Item::RangeDown => {
+                        bomb_satchel.bomb_range = bomb_satchel.bomb_range.saturating_sub(1).max(1);
+                    }
+                    Item::RemoteBomb => bomb_satchel.has_remote_detonator = true,
+                    Item::Pierce => bomb_satchel.has_pierce = true,
+                    Item::Magnet => {
+                        player.magnet_radius = player
+                            .magnet_radius
+                            .saturating_add(MAGNET_RADIUS_PER_ITEM)
+                            .min(MAX_MAGNET_RADIUS);
+                    }
+                    Item::Skull => {
+                        let skull_effect = SkullEffect::random(&mut session_rng);
+                        info!(
+                            "[frame:{}] Player {} got skull effect {skull_effect:?}",
+                            frame_count.frame, player.id.0,
+                        );
+                        match skull_effect {
+                            SkullEffect::HalveRange => {
+                                bomb_satchel.bomb_range = (bomb_satchel.bomb_range / 2).max(1);
+                            }
+                            SkullEffect::LosePush => player.can_push_bombs = false,
+                            SkullEffect::Stun => {
+                                player.stunned_until_frame =
+                                    frame_count.frame + SKULL_STUN_DURATION_FRAMES;
+                            }
+                        }
+                    }
                 };
+                *game_stats.items_collected.entry(player.id).or_insert(0) += 1;
+                if let Some(game_event_queue) = game_event_queue.as_mut() {
+                    game_event_queue.push(
+                        frame_count.frame,
+                        GameEventKind::ItemPickedUp {
+                            player_id: player.id.0,
+                            item,
+                        },
+                    );
+                }
 
                 commands.entity(item_entity).despawn_recursive();
             }
@@ -641,6 +1954,8 @@ pub fn pick_up_item(
                     item_entity,
                     &mut item_texture,
                     frame_count.frame,
+                    world_configs.get(*world_type).item_burn_duration_frames,
+                    0,
                 );
             }
             (None, Some(_)) => unreachable!(),
@@ -655,9 +1970,24 @@ pub fn bomb_drop(
     game_textures: Res<GameTextures>,
     fonts: Res<Fonts>,
     world_type: Res<WorldType>,
+    current_round_modifier: Res<CurrentRoundModifier>,
     rollback_ordered: Res<RollbackOrdered>,
-    mut alive_player_query: Query<(&Rollback, &Player, &Position, &mut BombSatchel), Without<Dead>>,
+    mut alive_player_query: Query<
+        (
+            &Rollback,
+            Entity,
+            &Player,
+            &Position,
+            &mut BombSatchel,
+            Option<&PendingBombDrop>,
+            Option<&HeldAction>,
+        ),
+        Without<Dead>,
+    >,
     invalid_bomb_position_query: Query<&Position, Or<(With<Solid>, With<BurningItem>)>>,
+    bomb_query: Query<&Bomb>,
+    game_config: Res<GameConfig>,
+    mut game_event_queue: Option<ResMut<GameEventQueue>>,
     frame_count: Res<FrameCount>,
     game_freeze: Option<Res<GameFreeze>>,
 ) {
@@ -667,6 +1997,12 @@ pub fn bomb_drop(
 
     let mut invalid_bomb_positions: HashSet<Position> =
         invalid_bomb_position_query.iter().copied().collect();
+    // a detonator only makes one remote bomb at a time
+    let mut remote_bomb_owners: HashSet<PlayerID> = bomb_query
+        .iter()
+        .filter(|bomb| bomb.is_remote)
+        .filter_map(|bomb| bomb.owner)
+        .collect();
 
     // player sorting is needed to ensure determinism of spawning bombs
     let mut players = alive_player_query
@@ -675,16 +2011,78 @@ pub fn bomb_drop(
         .collect_vec();
     // shuffle to ensure fairness in situations where two players try to place a bomb in the same frame
     shuffle(&mut players, &mut session_rng);
-    for (_, player, position, mut bomb_satchel) in players {
-        if inputs[player.id.0 as usize].0 .0 & INPUT_ACTION != 0
+    for (_, player_entity, player, position, mut bomb_satchel, pending_bomb_drop, held_action) in
+        players
+    {
+        if player.is_stunned(frame_count.frame) {
+            continue;
+        }
+
+        let input = inputs[player.id.0 as usize].0 .0;
+        let action_pressed = input & INPUT_ACTION != 0;
+        let action_confirmed = if game_config.hold_to_bomb {
+            // the press itself counts as held too, the mouse only sends a press
+            if action_pressed || input & INPUT_ACTION_HELD != 0 {
+                // saturate past the threshold so that a continued hold doesn't drop more bombs
+                let frames_held =
+                    (held_action.map_or(0, |h| h.frames_held) + 1).min(BOMB_HOLD_FRAMES + 1);
+                commands
+                    .entity(player_entity)
+                    .insert(HeldAction { frames_held });
+                frames_held == BOMB_HOLD_FRAMES
+            } else if let Some(held_action) = held_action {
+                commands.entity(player_entity).remove::<HeldAction>();
+                // a tap released before the hold threshold drops the bomb right away
+                held_action.frames_held < BOMB_HOLD_FRAMES
+            } else {
+                false
+            }
+        } else {
+            action_pressed
+        };
+        let wants_to_drop_bomb = action_confirmed || pending_bomb_drop.is_some();
+
+        if wants_to_drop_bomb
             && bomb_satchel.bombs_available > 0
-            && !invalid_bomb_positions.contains(position)
+            && invalid_bomb_positions.contains(position)
         {
+            match pending_bomb_drop {
+                None => {
+                    // the tile might get freed up shortly (e.g. a bomb is just being pushed away), so remember the drop
+                    commands.entity(player_entity).insert(PendingBombDrop {
+                        expires_frame: frame_count.frame + PENDING_BOMB_WINDOW,
+                    });
+                }
+                Some(pending_bomb_drop) if frame_count.frame >= pending_bomb_drop.expires_frame => {
+                    commands.entity(player_entity).remove::<PendingBombDrop>();
+                }
+                Some(_) => (),
+            }
+            continue;
+        }
+
+        if wants_to_drop_bomb && bomb_satchel.bombs_available > 0 {
+            if pending_bomb_drop.is_some() {
+                commands.entity(player_entity).remove::<PendingBombDrop>();
+            }
+
             info!(
                 "[frame:{}] Player {} placed a bomb at position: {:?}",
                 frame_count.frame, player.id.0, position
             );
             bomb_satchel.bombs_available -= 1;
+            let is_remote =
+                bomb_satchel.has_remote_detonator && remote_bomb_owners.insert(player.id);
+            if let Some(game_event_queue) = game_event_queue.as_mut() {
+                game_event_queue.push(
+                    frame_count.frame,
+                    GameEventKind::BombDropped {
+                        player_id: player.id.0,
+                        y: position.y,
+                        x: position.x,
+                    },
+                );
+            }
 
             commands
                 .spawn((
@@ -696,6 +2094,7 @@ pub fn bomb_drop(
                             BOMB_Z_LAYER,
                         ),
                         sprite: Sprite {
+                            color: bomb_tint(Some(player.id)),
                             custom_size: Some(Vec2::new(TILE_WIDTH as f32, TILE_HEIGHT as f32)),
                             ..Default::default()
                         },
@@ -703,9 +2102,20 @@ pub fn bomb_drop(
                     },
                     Bomb {
                         owner: Some(player.id),
-                        range: bomb_satchel.bomb_range,
-                        expiration_frame: frame_count.frame + 2 * FPS,
+                        range: match current_round_modifier.0 {
+                            RoundModifier::GiantBombs => bomb_satchel.bomb_range + 3,
+                            _ => bomb_satchel.bomb_range,
+                        },
+                        expiration_frame: frame_count.frame
+                            + match current_round_modifier.0 {
+                                RoundModifier::FuseRush => game_config.bomb_fuse_frames / 2,
+                                _ => game_config.bomb_fuse_frames,
+                            },
+                        is_remote,
+                        pierces_walls: bomb_satchel.has_pierce,
                     },
+                    ChainDepth::default(),
+                    ChainInitiator(Some(player.id)),
                     Solid,
                     *position,
                 ))
@@ -754,7 +2164,171 @@ pub fn bomb_drop(
                         .add_rollback();
                 });
 
-            invalid_bomb_positions.insert(*position);
+            invalid_bomb_positions.insert(*position);
+        } else if pending_bomb_drop.is_some() {
+            // no more bombs to place
+            commands.entity(player_entity).remove::<PendingBombDrop>();
+        }
+    }
+}
+
+pub fn update_bomb_count_overlay(
+    player_query: Query<(&BombSatchel, &Children), With<Player>>,
+    mut overlay_query: Query<&mut Text, With<BombCountOverlay>>,
+) {
+    for (bomb_satchel, children) in player_query.iter() {
+        let bomb_count = bomb_satchel.bombs_available.to_string();
+        for &child in children.iter() {
+            if let Ok(mut text) = overlay_query.get_mut(child) {
+                if text.sections[0].value != bomb_count {
+                    text.sections[0].value = bomb_count.clone();
+                }
+            }
+        }
+    }
+}
+
+pub fn remote_detonate_system(
+    inputs: Res<PlayerInputs<GgrsConfig>>,
+    alive_player_query: Query<&Player, Without<Dead>>,
+    mut bomb_query: Query<&mut Bomb>,
+    frame_count: Res<FrameCount>,
+    game_freeze: Option<Res<GameFreeze>>,
+) {
+    if game_freeze.is_some() {
+        return;
+    }
+
+    for player in alive_player_query.iter() {
+        if inputs[player.id.0 as usize].0 .0 & INPUT_DETONATE == 0 {
+            continue;
+        }
+
+        for mut bomb in bomb_query
+            .iter_mut()
+            .filter(|bomb| bomb.is_remote && bomb.owner == Some(player.id))
+        {
+            let detonation_frame = frame_count.frame + BOMB_SHORTENED_FUSE_FRAME_COUNT;
+            if bomb.expiration_frame > detonation_frame {
+                info!(
+                    "[frame:{}] Player {} remotely detonated a bomb",
+                    frame_count.frame, player.id.0
+                );
+                bomb.expiration_frame = detonation_frame;
+            }
+        }
+    }
+}
+
+pub fn emote_system(
+    mut commands: Commands,
+    inputs: Res<PlayerInputs<GgrsConfig>>,
+    fonts: Res<Fonts>,
+    rollback_ordered: Res<RollbackOrdered>,
+    alive_player_query: Query<(&Rollback, Entity, &Player), Without<Dead>>,
+    emote_query: Query<(Entity, &Emote, &Parent)>,
+    frame_count: Res<FrameCount>,
+    game_freeze: Option<Res<GameFreeze>>,
+) {
+    if game_freeze.is_some() {
+        return;
+    }
+
+    for (entity, emote, _) in emote_query.iter() {
+        if frame_count.frame >= emote.expiration_frame {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+
+    // player sorting is needed to ensure determinism of spawning emotes
+    for (_, player_entity, player) in alive_player_query
+        .iter()
+        .sorted_by_cached_key(|q| rollback_ordered.order(*q.0))
+    {
+        let input = inputs[player.id.0 as usize].0 .0;
+        let (emote_1, emote_2) = (input & INPUT_EMOTE_1 != 0, input & INPUT_EMOTE_2 != 0);
+        if !emote_1 && !emote_2 {
+            continue;
+        }
+
+        // the other emote input shortly after a single emote turns it into a combo
+        let combo_start_symbol = emote_query
+            .iter()
+            .filter(|(_, _, parent)| parent.get() == player_entity)
+            .map(|(_, emote, _)| emote)
+            .find(|emote| frame_count.frame < emote.shown_frame + EMOTE_COMBO_WINDOW_FRAMES)
+            .map(|emote| emote.symbol);
+        let symbol = match (emote_1, emote_2, combo_start_symbol) {
+            (true, true, _) | (false, true, Some(0)) => 2,
+            (true, false, Some(1)) => 3,
+            (true, false, _) => 0,
+            (false, _, _) => 1,
+        };
+
+        // a new emote replaces the one currently shown
+        for (entity, _, parent) in emote_query.iter() {
+            if parent.get() == player_entity {
+                commands.entity(entity).despawn_recursive();
+            }
+        }
+
+        commands.entity(player_entity).with_children(|parent| {
+            parent
+                .spawn((
+                    Text2dBundle {
+                        text: Text::from_section(
+                            EMOTE_SYMBOLS[symbol as usize],
+                            TextStyle {
+                                font: fonts.mono.clone(),
+                                font_size: 2.0 * PIXEL_SCALE as f32,
+                                color: COLORS[15].into(),
+                            },
+                        )
+                        .with_alignment(TextAlignment::Center),
+                        transform: Transform::from_xyz(0.0, TILE_HEIGHT as f32 * 0.75, 1.0),
+                        ..Default::default()
+                    },
+                    Emote {
+                        symbol,
+                        shown_frame: frame_count.frame,
+                        expiration_frame: frame_count.frame + EMOTE_DURATION_FRAMES,
+                    },
+                ))
+                .add_rollback();
+        });
+    }
+}
+
+// restores the owner tint, which also covers bombs whose flash got rolled back
+pub fn clear_bomb_tint(
+    mut bomb_query: Query<(&Bomb, &mut Sprite)>,
+    game_freeze: Option<Res<GameFreeze>>,
+) {
+    if game_freeze.is_some() {
+        return;
+    }
+
+    for (bomb, mut sprite) in bomb_query.iter_mut() {
+        sprite.color = bomb_tint(bomb.owner);
+    }
+}
+
+pub fn flash_near_expiry_bombs(
+    frame_count: Res<FrameCount>,
+    mut bomb_query: Query<(&Bomb, &mut Sprite)>,
+    game_freeze: Option<Res<GameFreeze>>,
+) {
+    if game_freeze.is_some() {
+        return;
+    }
+
+    for (bomb, mut sprite) in bomb_query.iter_mut() {
+        let remaining_frames = bomb.expiration_frame.saturating_sub(frame_count.frame);
+        // white is the default sprite color, so the flash uses a tint to be visible
+        if remaining_frames < BOMB_FLASH_FRAMES
+            && frame_count.frame % (2 * BOMB_FLASH_INTERVAL) < BOMB_FLASH_INTERVAL
+        {
+            sprite.color = COLORS[12].into();
         }
     }
 }
@@ -876,6 +2450,9 @@ pub fn crumbling_tick(
     frame_count: Res<FrameCount>,
     crumbling_query: Query<(Entity, &Crumbling, &Position)>,
     game_textures: Res<GameTextures>,
+    loot_table: Res<LootTable>,
+    current_round_modifier: Res<CurrentRoundModifier>,
+    mut game_event_queue: Option<ResMut<GameEventQueue>>,
     game_freeze: Option<Res<GameFreeze>>,
 ) {
     if game_freeze.is_some() {
@@ -888,11 +2465,118 @@ pub fn crumbling_tick(
         .sorted_unstable_by_key(|(_, _, &p)| p)
     {
         commands.entity(entity).despawn_recursive();
+        if let Some(game_event_queue) = game_event_queue.as_mut() {
+            game_event_queue.push(
+                frame_count.frame,
+                GameEventKind::WallCrumbled {
+                    y: position.y,
+                    x: position.x,
+                },
+            );
+        }
+
+        if let RoundModifier::NoItems = current_round_modifier.0 {
+            continue;
+        }
 
         // drop power-up
-        let roll = session_rng.gen_u64() % 100;
-        if roll < ITEM_SPAWN_CHANCE_PERCENTAGE {
-            generate_item_at_position(&mut session_rng, &mut commands, &game_textures, *position);
+        if session_rng.gen_bool(loot_table.spawn_chance, 100) {
+            generate_item_at_position(
+                &mut session_rng,
+                &mut commands,
+                &game_textures,
+                &loot_table,
+                *position,
+            );
+        }
+    }
+}
+
+pub fn crackable_ice_tick(
+    mut commands: Commands,
+    game_textures: Res<GameTextures>,
+    world_type: Res<WorldType>,
+    mut crackable_ice_query: Query<(Entity, &CrackableIce, &Position, &mut Handle<Image>)>,
+    solid_query: Query<&Position, With<Solid>>,
+    item_query: Query<(Entity, &Position), With<Item>>,
+    alive_player_query: Query<(Entity, &Player, &Position), Without<Dead>>,
+    mut game_event_queue: Option<ResMut<GameEventQueue>>,
+    frame_count: Res<FrameCount>,
+    game_freeze: Option<Res<GameFreeze>>,
+) {
+    if game_freeze.is_some() {
+        return;
+    }
+
+    let solid_positions: HashSet<Position> = solid_query.iter().copied().collect();
+    let map_textures = game_textures.get_map_textures(*world_type);
+    for (entity, crackable_ice, &position, mut texture) in crackable_ice_query.iter_mut() {
+        if crackable_ice.hp > 0 {
+            // set on every frame so the texture follows the ice state through rollbacks
+            let expected_texture = if crackable_ice.hp == 1 {
+                &map_textures.cracked_ice
+            } else {
+                &map_textures.empty
+            };
+            if *texture != *expected_texture {
+                *texture = expected_texture.clone();
+            }
+            continue;
+        }
+
+        // wait for bombs to move off the ice, only one solid can occupy a position
+        if solid_positions.contains(&position) {
+            continue;
+        }
+
+        commands.entity(entity).despawn_recursive();
+        commands
+            .spawn((
+                SpriteBundle {
+                    transform: Transform::from_xyz(
+                        get_x(position.x),
+                        get_y(position.y),
+                        CRACKABLE_ICE_Z_LAYER,
+                    ),
+                    sprite: Sprite {
+                        color: COLORS[0].into(),
+                        custom_size: Some(Vec2::new(TILE_WIDTH as f32, TILE_HEIGHT as f32)),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                Solid,
+                position,
+            ))
+            .add_rollback();
+
+        for (item_entity, _) in item_query.iter().filter(|(_, &p)| p == position) {
+            commands.entity(item_entity).despawn_recursive();
+        }
+
+        for (player_entity, player, _) in
+            alive_player_query.iter().filter(|(_, _, &p)| p == position)
+        {
+            info!(
+                "[frame:{}] Player {} fell through the ice at position: {position:?}",
+                frame_count.frame, player.id.0
+            );
+            commands.entity(player_entity).insert((
+                Dead {
+                    cleanup_frame: frame_count.frame + MAX_PREDICTED_FRAMES,
+                },
+                DeathAnimation { blink_frame: 0 },
+            ));
+            if let Some(game_event_queue) = game_event_queue.as_mut() {
+                game_event_queue.push(
+                    frame_count.frame,
+                    GameEventKind::PlayerDied {
+                        player_id: player.id.0,
+                        y: position.y,
+                        x: position.x,
+                    },
+                );
+            }
         }
     }
 }
@@ -918,10 +2602,18 @@ pub fn burning_item_tick(
 pub fn explode_bombs(
     mut commands: Commands,
     world_type: Res<WorldType>,
+    world_configs: Res<WorldConfigs>,
     game_textures: Res<GameTextures>,
     rollback_ordered: Res<RollbackOrdered>,
     mut position_queries: ParamSet<(
-        Query<(&Rollback, Entity, &mut Bomb, &Position)>,
+        Query<(
+            &Rollback,
+            Entity,
+            &mut Bomb,
+            &mut ChainDepth,
+            &mut ChainInitiator,
+            &Position,
+        )>,
         Query<(Entity, &Position, Option<&Bomb>), With<Solid>>,
     )>,
     mut alive_player_query: Query<(&Player, &mut BombSatchel), Without<Dead>>,
@@ -930,6 +2622,10 @@ pub fn explode_bombs(
         (With<Wall>, With<Destructible>),
     >,
     fire_query: Query<(&Rollback, Entity, &Position), With<Fire>>,
+    map_size: Res<MapSize>,
+    mut game_stats: ResMut<GameStats>,
+    mut bomb_exploded_events: EventWriter<BombExplodedEvent>,
+    mut game_event_queue: Option<ResMut<GameEventQueue>>,
     frame_count: Res<FrameCount>,
     game_freeze: Option<Res<GameFreeze>>,
 ) {
@@ -951,46 +2647,83 @@ pub fn explode_bombs(
         .copied()
         .collect();
 
+    let fire_duration_frames = world_configs.get(*world_type).fire_duration_frames;
     let mut fire_touched_positions = HashSet::new();
-    let spawn_fire = |commands: &mut Commands, position: Position| {
-        // remove previous fire at position if it exists
+    let mut fire_count = fire_query.iter().count();
+    let mut despawned_fires = HashSet::new();
+    let mut fire_limit_warned = false;
+    let mut spawn_fire = |commands: &mut Commands, position: Position, owner: Option<PlayerID>| {
+        // remove previous fire at position if it exists, several bombs can reach the same one
         for (_, e, _) in fire_query.iter().filter(|(_, _, &p)| p == position) {
-            commands.entity(e).despawn_recursive();
+            if despawned_fires.insert(e) {
+                commands.entity(e).despawn_recursive();
+                fire_count -= 1;
+            }
         }
 
-        commands
-            .spawn((
-                SpriteBundle {
-                    texture: game_textures.fire.clone(),
-                    transform: Transform::from_xyz(
-                        get_x(position.x),
-                        get_y(position.y),
-                        FIRE_Z_LAYER,
-                    ),
-                    sprite: Sprite {
-                        custom_size: Some(Vec2::new(TILE_WIDTH as f32, TILE_HEIGHT as f32)),
+        if fire_count < MAX_FIRE_ENTITIES {
+            let fire = Fire {
+                expiration_frame: frame_count.frame + fire_duration_frames,
+                owner,
+            };
+            commands
+                .spawn((
+                    SpriteBundle {
+                        texture: game_textures.fire.clone(),
+                        transform: Transform::from_xyz(
+                            get_x(position.x),
+                            get_y(position.y),
+                            FIRE_Z_LAYER,
+                        ),
+                        sprite: Sprite {
+                            custom_size: Some(Vec2::new(TILE_WIDTH as f32, TILE_HEIGHT as f32)),
+                            ..Default::default()
+                        },
                         ..Default::default()
                     },
-                    ..Default::default()
-                },
-                Fire {
-                    expiration_frame: frame_count.frame + FPS / 2,
-                },
-                position,
-            ))
-            .add_rollback();
+                    fire,
+                    position,
+                ))
+                .add_rollback();
+            fire_count += 1;
+        } else if !fire_limit_warned {
+            warn!(
+                "[frame:{}] Fire limit of {MAX_FIRE_ENTITIES} reached, skipping new fires",
+                frame_count.frame
+            );
+            fire_limit_warned = true;
+        }
     };
 
     // sorting is needed to ensure fire spawn determinism
     let tmp = position_queries.p0();
     let exploding_bombs = tmp
         .iter()
-        .filter(|(_, _, b, _)| frame_count.frame >= b.expiration_frame)
+        .filter(|(_, _, b, _, _, _)| frame_count.frame >= b.expiration_frame)
         .sorted_by_cached_key(|q| rollback_ordered.order(*q.0))
-        .map(|(_, e, &b, &p)| (e, b, p))
+        .map(|(_, e, &b, &c, &i, &p)| (e, b, c, i, p))
         .collect_vec();
-    for (entity, bomb, position) in exploding_bombs {
+    for (entity, bomb, chain_depth, chain_initiator, position) in exploding_bombs {
+        bomb_exploded_events.send(BombExplodedEvent {
+            chain_depth: chain_depth.0,
+        });
         commands.entity(entity).despawn_recursive();
+        if let Some(game_event_queue) = game_event_queue.as_mut() {
+            game_event_queue.push(
+                frame_count.frame,
+                GameEventKind::BombExploded {
+                    owner: bomb.owner.map(|owner| owner.0),
+                    y: position.y,
+                    x: position.x,
+                },
+            );
+        }
+
+        // the depth counts the bombs set off before this one
+        if let (true, Some(initiator)) = (chain_depth.0 > 0, chain_initiator.0) {
+            let longest_chain = game_stats.longest_chain.entry(initiator).or_insert(0);
+            *longest_chain = (*longest_chain).max(chain_depth.0 as u32 + 1);
+        }
 
         if let Some(owner) = bomb.owner {
             if let Some((_, mut bomb_satchel)) = alive_player_query
@@ -998,26 +2731,39 @@ pub fn explode_bombs(
                 .find(|(player, _)| player.id == owner)
             {
                 bomb_satchel.bombs_available += 1;
+                if bomb.is_remote {
+                    bomb_satchel.has_remote_detonator = false;
+                }
             }
         }
 
         if !fire_touched_positions.contains(&position) {
-            spawn_fire(&mut commands, position);
+            spawn_fire(&mut commands, position, bomb.owner);
             fire_touched_positions.insert(position);
         }
         for direction in Direction::LIST {
-            for position in (1..=bomb.range).map(|i| position.offset(direction, i)) {
+            let mut pierced = false;
+            for position in position
+                .iter_in_direction(direction, bomb.range as usize)
+                .take_while(|p| p.in_bounds(*map_size))
+            {
                 if fireproof_positions.contains(&position) {
                     if !fire_touched_positions.contains(&position) {
                         // bomb burn
+                        // the further down the chain a bomb is the later it explodes, so that the chain propagation is visible
+                        let child_chain_depth = (chain_depth.0 + 1).min(MAX_CHAIN_DEPTH);
+                        let chained_expiration_frame = frame_count.frame
+                            + BOMB_SHORTENED_FUSE_FRAME_COUNT * (child_chain_depth as u32 + 1);
                         position_queries
                             .p0()
                             .iter_mut()
-                            .filter(|(_, _, _, &bomb_position)| bomb_position == position)
-                            .for_each(|(_, _, mut bomb, _)| {
-                                bomb.expiration_frame = bomb
-                                    .expiration_frame
-                                    .min(frame_count.frame + BOMB_SHORTENED_FUSE_FRAME_COUNT);
+                            .filter(|(_, _, _, _, _, &bomb_position)| bomb_position == position)
+                            .for_each(|(_, _, mut bomb, mut chain_depth, mut initiator, _)| {
+                                if chained_expiration_frame < bomb.expiration_frame {
+                                    bomb.expiration_frame = chained_expiration_frame;
+                                    *chain_depth = ChainDepth(child_chain_depth);
+                                    *initiator = chain_initiator;
+                                }
                             });
 
                         // destructible wall burn
@@ -1038,11 +2784,22 @@ pub fn explode_bombs(
 
                         fire_touched_positions.insert(position);
                     }
+
+                    // only a single destructible wall is pierced per direction
+                    if bomb.pierces_walls
+                        && !pierced
+                        && destructible_wall_query
+                            .iter()
+                            .any(|(_, &wall_position, _, _)| wall_position == position)
+                    {
+                        pierced = true;
+                        continue;
+                    }
                     break;
                 }
 
                 if !fire_touched_positions.contains(&position) {
-                    spawn_fire(&mut commands, position);
+                    spawn_fire(&mut commands, position, bomb.owner);
                     fire_touched_positions.insert(position);
                 }
             }
@@ -1050,10 +2807,62 @@ pub fn explode_bombs(
     }
 }
 
+pub fn log_wall_of_death_done(
+    mut wall_of_death_done_events: EventReader<WallOfDeathDone>,
+    mut game_event_queue: Option<ResMut<GameEventQueue>>,
+) {
+    for event in wall_of_death_done_events.read() {
+        if let Some(game_event_queue) = game_event_queue.as_mut() {
+            game_event_queue.push(event.frame, GameEventKind::WallOfDeathDone);
+        }
+    }
+}
+
+pub fn animate_death(
+    mut dead_player_query: Query<(&Dead, &mut DeathAnimation, &mut Sprite)>,
+    frame_count: Res<FrameCount>,
+    game_freeze: Option<Res<GameFreeze>>,
+) {
+    if game_freeze.is_some() {
+        return;
+    }
+
+    for (dead, mut death_animation, mut sprite) in dead_player_query.iter_mut() {
+        let blink_on = (death_animation.blink_frame as u32 / DEATH_BLINK_INTERVAL) % 2 == 1;
+        let mut color = if blink_on {
+            COLORS[4].into()
+        } else {
+            Color::WHITE
+        };
+
+        // fade out during the second half of the time left until cleanup
+        let fade_start_frame = dead.cleanup_frame.saturating_sub(MAX_PREDICTED_FRAMES / 2);
+        if frame_count.frame >= fade_start_frame {
+            let fade_frames = dead.cleanup_frame - fade_start_frame;
+            let frames_left = dead.cleanup_frame.saturating_sub(frame_count.frame);
+            color.set_a(frames_left as f32 / fade_frames as f32);
+        }
+
+        sprite.color = color;
+        death_animation.blink_frame = death_animation.blink_frame.saturating_add(1);
+    }
+}
+
 pub fn player_burn(
     mut commands: Commands,
-    fire_query: Query<&Position, With<Fire>>,
-    alive_player_query: Query<(Entity, &Player, &Position), Without<Dead>>,
+    leaderboard: Res<Leaderboard>,
+    fire_query: Query<(&Fire, &Position)>,
+    mut alive_player_query: Query<
+        (
+            Entity,
+            &Player,
+            &Position,
+            Option<&mut PlayerHP>,
+            Option<&InvulnFrames>,
+        ),
+        Without<Dead>,
+    >,
+    mut game_event_queue: Option<ResMut<GameEventQueue>>,
     frame_count: Res<FrameCount>,
     game_freeze: Option<Res<GameFreeze>>,
 ) {
@@ -1061,18 +2870,63 @@ pub fn player_burn(
         return;
     }
 
-    let fire_positions: HashSet<Position> = fire_query.iter().copied().collect();
-    alive_player_query
+    let fire_owners: HashMap<Position, Option<PlayerID>> = fire_query
         .iter()
-        .filter(|(_, _, position)| fire_positions.contains(*position))
-        .for_each(|(entity, player, position)| {
+        .map(|(fire, &position)| (position, fire.owner))
+        .collect();
+    alive_player_query
+        .iter_mut()
+        .filter(
+            |(_, player, position, _, _)| match fire_owners.get(*position) {
+                None => false,
+                // fire from a teammate's bomb is harmless, while a player's own bombs can still kill them
+                Some(Some(owner)) if *owner != player.id => {
+                    player.team.is_none() || leaderboard.teams.get(owner) != player.team.as_ref()
+                }
+                Some(_) => true,
+            },
+        )
+        .for_each(|(entity, player, position, player_hp, invuln_frames)| {
+            if let Some(mut player_hp) = player_hp {
+                let invulnerable =
+                    invuln_frames.is_some_and(|invuln_frames| frame_count.frame < invuln_frames.0);
+                if invulnerable {
+                    return;
+                }
+
+                player_hp.current = player_hp.current.saturating_sub(1);
+                if player_hp.current > 0 {
+                    info!(
+                        "[frame:{}] Player {} was hit at position: {position:?}, {} HP left",
+                        frame_count.frame, player.id.0, player_hp.current
+                    );
+                    commands
+                        .entity(entity)
+                        .insert(InvulnFrames(frame_count.frame + RESPAWN_INVULN_FRAMES));
+                    return;
+                }
+            }
+
             info!(
                 "[frame:{}] Player {} was burned at position: {position:?}",
                 frame_count.frame, player.id.0
             );
-            commands.entity(entity).insert(Dead {
-                cleanup_frame: frame_count.frame + MAX_PREDICTED_FRAMES,
-            });
+            commands.entity(entity).insert((
+                Dead {
+                    cleanup_frame: frame_count.frame + MAX_PREDICTED_FRAMES,
+                },
+                DeathAnimation { blink_frame: 0 },
+            ));
+            if let Some(game_event_queue) = game_event_queue.as_mut() {
+                game_event_queue.push(
+                    frame_count.frame,
+                    GameEventKind::PlayerDied {
+                        player_id: player.id.0,
+                        y: position.y,
+                        x: position.x,
+                    },
+                );
+            }
         });
 }
 
@@ -1086,42 +2940,201 @@ pub fn bomb_burn(
         return;
     }
 
-    let fire_positions: HashSet<Position> = fire_query.iter().copied().collect();
-    bomb_query
-        .iter_mut()
-        .filter(|(_, position)| fire_positions.contains(*position))
-        .for_each(|(mut bomb, _)| {
-            bomb.expiration_frame = bomb
-                .expiration_frame
-                .min(frame_count.frame + BOMB_SHORTENED_FUSE_FRAME_COUNT);
-        });
+    let fire_positions: HashSet<Position> = fire_query.iter().copied().collect();
+    bomb_query
+        .iter_mut()
+        .filter(|(_, position)| fire_positions.contains(*position))
+        .for_each(|(mut bomb, _)| {
+            bomb.expiration_frame = bomb
+                .expiration_frame
+                .min(frame_count.frame + BOMB_SHORTENED_FUSE_FRAME_COUNT);
+        });
+}
+
+pub fn item_burn(
+    mut commands: Commands,
+    game_textures: Res<GameTextures>,
+    world_type: Res<WorldType>,
+    world_configs: Res<WorldConfigs>,
+    fire_query: Query<&Position, With<Fire>>,
+    mut item_query: Query<(Entity, &Position, &mut Handle<Image>), With<Item>>,
+    frame_count: Res<FrameCount>,
+    game_freeze: Option<Res<GameFreeze>>,
+) {
+    if game_freeze.is_some() {
+        return;
+    }
+
+    let fire_positions: HashSet<Position> = fire_query.iter().copied().collect();
+    item_query
+        .iter_mut()
+        .filter(|(_, position, _)| fire_positions.contains(*position))
+        .for_each(|(entity, _, mut texture)| {
+            burn_item(
+                &mut commands,
+                &game_textures,
+                entity,
+                &mut texture,
+                frame_count.frame,
+                world_configs.get(*world_type).item_burn_duration_frames,
+                0,
+            );
+        });
+}
+
+pub fn chain_burn(
+    mut commands: Commands,
+    game_textures: Res<GameTextures>,
+    world_type: Res<WorldType>,
+    world_configs: Res<WorldConfigs>,
+    rollback_ordered: Res<RollbackOrdered>,
+    mut session_rng: ResMut<SessionRng>,
+    chain_burn_depth: Res<ChainBurnDepth>,
+    burning_item_query: Query<(&BurningItem, &Position)>,
+    mut item_query: Query<(&Rollback, Entity, &Position, &mut Handle<Image>), With<Item>>,
+    frame_count: Res<FrameCount>,
+    game_freeze: Option<Res<GameFreeze>>,
+) {
+    if game_freeze.is_some() {
+        return;
+    }
+
+    let mut burning_positions: HashMap<Position, u8> = HashMap::new();
+    for (burning_item, position) in burning_item_query.iter() {
+        let hop = burning_positions
+            .entry(*position)
+            .or_insert(burning_item.hop);
+        *hop = (*hop).min(burning_item.hop);
+    }
+
+    let mut remaining_items: Vec<(&Rollback, Entity, Position)> = item_query
+        .iter()
+        .map(|(rollback, entity, position, _)| (rollback, entity, *position))
+        .collect();
+    remaining_items.sort_by_key(|(rollback, _, _)| rollback_ordered.order(**rollback));
+
+    let mut burned_items = vec![];
+    for hop in 0..chain_burn_depth.0 {
+        let source_positions: Vec<Position> = burning_positions
+            .iter()
+            .filter(|(_, &h)| h == hop)
+            .map(|(position, _)| *position)
+            .collect();
+        if source_positions.is_empty() {
+            continue;
+        }
+
+        let (mut reached_items, unreached_items): (Vec<_>, Vec<_>) =
+            remaining_items.into_iter().partition(|(_, _, position)| {
+                source_positions
+                    .iter()
+                    .any(|sp| sp.manhattan_distance(position) == 1)
+            });
+        remaining_items = unreached_items;
+
+        // break ties between equidistant items
+        shuffle(&mut reached_items, &mut session_rng);
+        for (_, entity, position) in reached_items {
+            burning_positions.entry(position).or_insert(hop + 1);
+            burned_items.push((entity, hop + 1));
+        }
+    }
+
+    for (entity, hop) in burned_items {
+        let (_, _, _, mut texture) = item_query.get_mut(entity).unwrap();
+        burn_item(
+            &mut commands,
+            &game_textures,
+            entity,
+            &mut texture,
+            frame_count.frame,
+            world_configs.get(*world_type).item_burn_duration_frames,
+            hop,
+        );
+    }
+}
+
+pub fn bomb_warning_overlay(
+    mut commands: Commands,
+    frame_count: Res<FrameCount>,
+    map_size: Res<MapSize>,
+    bomb_query: Query<(&Bomb, &Position)>,
+    solid_query: Query<&Position, (With<Solid>, Without<Bomb>)>,
+    overlay_query: Query<Entity, With<BombWarningOverlay>>,
+) {
+    for entity in overlay_query.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    let warned_bombs = bomb_query
+        .iter()
+        .filter(|(bomb, _)| {
+            bomb.expiration_frame.saturating_sub(frame_count.frame) < BOMB_WARNING_FRAMES
+        })
+        .sorted_by_key(|(bomb, position)| (bomb.expiration_frame, **position))
+        .take(MAX_BOMB_WARNING_BOMBS)
+        .collect_vec();
+    if warned_bombs.is_empty() {
+        return;
+    }
+
+    // fire stops at solids, so only the open tiles are at risk
+    let solid_positions: HashSet<Position> = solid_query.iter().copied().collect();
+    let warned_positions: HashSet<Position> = warned_bombs
+        .into_iter()
+        .flat_map(|(bomb, &bomb_position)| {
+            blast_positions(bomb_position, bomb.range, *map_size, &solid_positions)
+        })
+        .collect();
+
+    // triangle wave pulse
+    let pulse_frames = FPS / 4;
+    let phase = (frame_count.frame % pulse_frames) as f32 / pulse_frames as f32;
+    let alpha = 0.1 + 0.3 * (1.0 - (2.0 * phase - 1.0).abs());
+    for position in warned_positions {
+        commands.spawn((
+            SpriteBundle {
+                transform: Transform::from_xyz(get_x(position.x), get_y(position.y), FIRE_Z_LAYER),
+                sprite: Sprite {
+                    color: Color::from(COLORS[4]).with_a(alpha),
+                    custom_size: Some(Vec2::new(TILE_WIDTH as f32, TILE_HEIGHT as f32)),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            BombWarningOverlay,
+        ));
+    }
 }
 
-pub fn item_burn(
+pub fn wall_of_death_progress_overlay(
     mut commands: Commands,
-    game_textures: Res<GameTextures>,
-    fire_query: Query<&Position, With<Fire>>,
-    mut item_query: Query<(Entity, &Position, &mut Handle<Image>), With<Item>>,
-    frame_count: Res<FrameCount>,
-    game_freeze: Option<Res<GameFreeze>>,
+    wall_of_death_consumed: Res<WallOfDeathConsumed>,
+    overlay_cell_query: Query<&WallOfDeathOverlayCell>,
 ) {
-    if game_freeze.is_some() {
+    if !wall_of_death_consumed.is_changed() {
         return;
     }
 
-    let fire_positions: HashSet<Position> = fire_query.iter().copied().collect();
-    item_query
-        .iter_mut()
-        .filter(|(_, position, _)| fire_positions.contains(*position))
-        .for_each(|(entity, _, mut texture)| {
-            burn_item(
-                &mut commands,
-                &game_textures,
-                entity,
-                &mut texture,
-                frame_count.frame,
-            );
-        });
+    let overlaid_positions: HashSet<Position> =
+        overlay_cell_query.iter().map(|cell| cell.0).collect();
+    for &position in wall_of_death_consumed.0.difference(&overlaid_positions) {
+        commands.spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    left: Val::Px((position.x as u32 * TILE_WIDTH) as f32),
+                    top: Val::Px((HUD_HEIGHT + position.y as u32 * TILE_HEIGHT) as f32),
+                    width: Val::Px(TILE_WIDTH as f32),
+                    height: Val::Px(TILE_HEIGHT as f32),
+                    ..Default::default()
+                },
+                background_color: Color::from(COLORS[4]).with_a(0.05).into(),
+                ..Default::default()
+            },
+            WallOfDeathOverlayCell(position),
+        ));
+    }
 }
 
 pub fn wall_of_death_update(
@@ -1133,6 +3146,9 @@ pub fn wall_of_death_update(
     indestructible_wall_query: Query<&Position, (With<Wall>, Without<Destructible>)>,
     entity_query: Query<(Entity, &Position, Option<&Bomb>)>,
     mut player_query: Query<(&Player, &mut BombSatchel, Option<&Dead>)>,
+    game_config: Res<GameConfig>,
+    mut wall_of_death_consumed: ResMut<WallOfDeathConsumed>,
+    mut wall_of_death_done_events: EventWriter<WallOfDeathDone>,
     frame_count: Res<FrameCount>,
     game_freeze: Option<Res<GameFreeze>>,
 ) {
@@ -1178,7 +3194,12 @@ pub fn wall_of_death_update(
                     _ => (),
                 }
 
-                position = position.offset(direction, 1);
+                match position.checked_offset(direction, 1) {
+                    Some(next_position) if next_position.in_bounds(*map_size) => {
+                        position = next_position
+                    }
+                    _ => break None,
+                }
                 if !indestructible_walls.contains(&position) {
                     break Some((position, direction));
                 }
@@ -1235,6 +3256,7 @@ pub fn wall_of_death_update(
                 position,
             ))
             .add_rollback();
+        wall_of_death_consumed.0.insert(position);
     };
 
     loop {
@@ -1264,14 +3286,21 @@ pub fn wall_of_death_update(
                     if let Some((next_position, next_direction)) =
                         get_next_position_direction(*position, *direction)
                     {
+                        clear_position_and_spawn_wall(next_position);
+
                         *position = next_position;
                         *direction = next_direction;
-                        *next_step_frame += FPS / 5;
-
-                        clear_position_and_spawn_wall(*position);
+                        // counted from the current frame so that the wall doesn't rush to catch up after a freeze
+                        *next_step_frame =
+                            frame_count.frame + game_config.wall_of_death_step_interval;
 
                         None
                     } else {
+                        info!("[frame:{}] Wall of Death done!", frame_count.frame);
+                        wall_of_death_done_events.send(WallOfDeathDone {
+                            frame: frame_count.frame,
+                        });
+
                         Some(WallOfDeath::Done)
                     }
                 } else {
@@ -1292,7 +3321,7 @@ pub fn wall_of_death_update(
 pub fn cleanup_dead(
     mut session_rng: ResMut<SessionRng>,
     mut commands: Commands,
-    dead_entity_query: Query<(Entity, &Dead)>,
+    dead_entity_query: Query<(Entity, &Dead, &Position, Option<&BombSatchel>)>,
     invalid_item_position_query: Query<
         &Position,
         Or<(
@@ -1305,18 +3334,38 @@ pub fn cleanup_dead(
     >,
     frame_count: Res<FrameCount>,
     game_textures: Res<GameTextures>,
+    loot_table: Res<LootTable>,
     map_size: Res<MapSize>,
+    game_config: Res<GameConfig>,
     game_freeze: Option<Res<GameFreeze>>,
 ) {
     if game_freeze.is_some() {
         return;
     }
 
-    for (e, d) in dead_entity_query.iter() {
+    for (e, d, dead_position, bomb_satchel) in dead_entity_query.iter() {
         if frame_count.frame >= d.cleanup_frame {
             commands.entity(e).despawn_recursive();
 
-            // death pinata
+            // return the collected power-ups back into the game
+            let satchel_items = bomb_satchel
+                .map(|bomb_satchel| {
+                    std::iter::repeat(Item::RangeUp)
+                        .take(bomb_satchel.bomb_range.saturating_sub(2) as usize)
+                        .chain(
+                            std::iter::repeat(Item::BombsUp)
+                                .take(bomb_satchel.bombs_available.saturating_sub(1) as usize),
+                        )
+                        .take(SATCHEL_DROP_MAX_ITEM_COUNT)
+                        .collect_vec()
+                })
+                .unwrap_or_default();
+
+            let pinata_count = game_config.pinata_count as usize;
+            if pinata_count == 0 && satchel_items.is_empty() {
+                continue;
+            }
+
             let invalid_item_positions: HashSet<Position> =
                 invalid_item_position_query.iter().copied().collect();
             let mut valid_positions = (1..map_size.rows - 1)
@@ -1324,57 +3373,231 @@ pub fn cleanup_dead(
                 .filter(|position| !invalid_item_positions.contains(position))
                 .collect_vec();
             shuffle(&mut valid_positions, &mut session_rng);
-            for &position in valid_positions.iter().take(3) {
+            // prefer the tiles next to the dead player, the stable sort keeps the shuffled order otherwise
+            let dead_neighbors = dead_position.neighbors();
+            valid_positions.sort_by_key(|position| !dead_neighbors.contains(&Some(*position)));
+            let mut valid_positions = valid_positions.into_iter();
+
+            // death pinata, without curses so that dying isn't punished twice
+            let pinata_loot_table = loot_table.without_curses();
+            for position in valid_positions.by_ref().take(pinata_count) {
                 generate_item_at_position(
                     &mut session_rng,
                     &mut commands,
                     &game_textures,
+                    &pinata_loot_table,
                     position,
                 );
             }
+
+            // satchel contents
+            for (position, item) in valid_positions.zip(satchel_items) {
+                spawn_item(&mut commands, &game_textures, position, item);
+            }
         }
     }
 }
 
 pub fn check_game_rules(
+    mut game_integrity_violation: ResMut<GameIntegrityViolation>,
     solid_object_query: Query<&Position, With<Solid>>,
-    fire_query: Query<&Position, With<Fire>>,
+    fire_query: Query<(&Fire, &Position)>,
     item_query: Query<&Position, With<Item>>,
+    non_solid_bomb_query: Query<&Position, (With<Bomb>, Without<Solid>)>,
+    satchelless_player_query: Query<&Player, Without<BombSatchel>>,
+    moving_bomb_query: Query<(&Moving, &Position), With<Bomb>>,
+    solid_dead_player_query: Query<&Player, (With<Dead>, With<Solid>)>,
+    frame_count: Res<FrameCount>,
 ) {
+    let mut violations = 0;
+    let mut report_violation = |message: String| {
+        violations += 1;
+        if cfg!(debug_assertions) {
+            error!("{message}");
+        } else {
+            warn!("{message}");
+        }
+    };
+
     for position in solid_object_query.iter().duplicates() {
-        warn!("Multiple solid objects at position: {position:?}");
+        report_violation(format!("Multiple solid objects at position: {position:?}"));
     }
-    for position in fire_query.iter().duplicates() {
-        warn!("Multiple fires at position: {position:?}");
+    for position in fire_query.iter().map(|(_, p)| p).duplicates() {
+        report_violation(format!("Multiple fires at position: {position:?}"));
     }
     for position in item_query.iter().duplicates() {
-        warn!("Multiple items at position: {position:?}");
+        report_violation(format!("Multiple items at position: {position:?}"));
+    }
+    for position in non_solid_bomb_query.iter() {
+        report_violation(format!("Non-solid bomb at position: {position:?}"));
+    }
+    for player in satchelless_player_query.iter() {
+        report_violation(format!("Player {} has no bomb satchel", player.id.0));
+    }
+    for (_, position) in fire_query
+        .iter()
+        .filter(|(fire, _)| fire.expiration_frame > frame_count.frame + FPS)
+    {
+        report_violation(format!("Fire lasting too long at position: {position:?}"));
+    }
+    let moving_bombs = moving_bomb_query
+        .iter()
+        .map(|(moving, &position)| (position, moving.direction))
+        .into_group_map();
+    for (position, directions) in moving_bombs
+        .iter()
+        .sorted_by_key(|(position, _)| **position)
+    {
+        // bombs that ran into one another ended up on the same position
+        if directions
+            .iter()
+            .any(|direction| directions.contains(&direction.opposite()))
+        {
+            report_violation(format!("Head-on moving bombs at position: {position:?}"));
+        }
+    }
+    for player in solid_dead_player_query.iter() {
+        report_violation(format!("Dead player {} is solid", player.id.0));
+    }
+
+    game_integrity_violation.frame = frame_count.frame;
+    game_integrity_violation.count = violations;
+    game_integrity_violation
+        .pending
+        .insert(frame_count.frame, violations);
+}
+
+pub fn confirm_game_integrity_violations(
+    mut game_integrity_violation: ResMut<GameIntegrityViolation>,
+    session: Option<Res<Session<GgrsConfig>>>,
+    frame_count: Res<FrameCount>,
+) {
+    let Some(confirmed_frame) = session
+        .as_deref()
+        .and_then(|session| confirmed_frame(session, &frame_count))
+    else {
+        return;
+    };
+
+    let confirmed_violations: u32 = game_integrity_violation
+        .pending
+        .iter()
+        .filter(|(&frame, _)| frame <= confirmed_frame)
+        .map(|(_, &count)| count)
+        .sum();
+    game_integrity_violation
+        .pending
+        .retain(|&frame, _| frame > confirmed_frame);
+    game_integrity_violation.total += confirmed_violations;
+}
+
+// only announced once the overtime is confirmed, the message isn't rolled back
+pub fn overtime_message_system(
+    mut commands: Commands,
+    fonts: Res<Fonts>,
+    session: Option<Res<Session<GgrsConfig>>>,
+    frame_count: Res<FrameCount>,
+    overtime_active: Option<Res<OvertimeActive>>,
+    freeze_message_query: Query<(), With<FreezeMessageDisplay>>,
+    primary_window_query: Query<&Window, With<PrimaryWindow>>,
+) {
+    let (Some(session), Some(overtime_active)) = (session, overtime_active) else {
+        return;
+    };
+
+    let announcement_frame = overtime_active.announcement_frame;
+    // the message is despawned when the overtime freeze ends
+    if frame_count.frame >= announcement_frame + OVERTIME_MESSAGE_FRAME_COUNT
+        || !freeze_message_query.is_empty()
+    {
+        return;
+    }
+
+    if confirmed_frame(&session, &frame_count)
+        .is_some_and(|confirmed_frame| confirmed_frame >= announcement_frame)
+    {
+        setup_freeze_message_display(
+            &mut commands,
+            primary_window_query.single(),
+            &fonts,
+            "OVERTIME!",
+        );
     }
 }
 
 pub fn finish_round(
     mut commands: Commands,
+    game_config: Res<GameConfig>,
     alive_player_query: Query<&Player, Without<Dead>>,
     frame_count: Res<FrameCount>,
-    game_end_frame: Option<Res<GameEndFrame>>,
+    mut game_end_frame: Option<ResMut<GameEndFrame>>,
+    wall_of_death: Option<ResMut<WallOfDeath>>,
+    overtime_active: Option<Res<OvertimeActive>>,
+    slow_motion_effect: Option<Res<SlowMotionEffect>>,
     game_freeze: Option<Res<GameFreeze>>,
+    session_time_up: Option<Res<SessionTimeUp>>,
 ) {
     if game_freeze.is_some() {
         return;
     }
 
-    let game_end_frame = game_end_frame.unwrap();
+    let game_end_frame = game_end_frame.as_mut().unwrap();
+    // the running round is cut short once the session time limit is reached
+    let time_up = frame_count.frame >= game_end_frame.0 || session_time_up.is_some();
 
-    let round_outcome =
-        if frame_count.frame >= game_end_frame.0 || alive_player_query.iter().count() == 0 {
-            Some(RoundOutcome::Tie)
-        } else if let Ok(player) = alive_player_query.get_single() {
-            Some(RoundOutcome::Winner(player.id))
-        } else {
-            None
-        };
+    // teamless players are a side of their own
+    let alive_sides = alive_player_query
+        .iter()
+        .map(|player| player.team.ok_or(player.id))
+        .unique()
+        .collect_vec();
+
+    if time_up
+        && session_time_up.is_none()
+        && alive_sides.len() > 1
+        && game_config.overtime_enabled
+        && overtime_active.is_none()
+    {
+        info!("[frame:{}] Overtime!", frame_count.frame);
+
+        let overtime_start_frame = frame_count.frame + OVERTIME_MESSAGE_FRAME_COUNT;
+        game_end_frame.0 = overtime_start_frame + game_config.overtime_frames;
+
+        // bring in the wall of death right away
+        let mut wall_of_death = wall_of_death.unwrap();
+        if let WallOfDeath::Dormant { .. } = *wall_of_death {
+            *wall_of_death = WallOfDeath::Dormant {
+                activation_frame: overtime_start_frame,
+            };
+        }
+
+        commands.insert_resource(OvertimeActive {
+            announcement_frame: frame_count.frame,
+        });
+        commands.insert_resource(GameFreeze {
+            end_frame: overtime_start_frame,
+            post_freeze_action: None,
+        });
+        return;
+    }
+
+    let round_outcome = if time_up || alive_sides.is_empty() {
+        Some(RoundOutcome::Tie)
+    } else {
+        match alive_sides[..] {
+            [Ok(team)] => Some(RoundOutcome::TeamWinner(team)),
+            [Err(player_id)] => Some(RoundOutcome::Winner(player_id)),
+            _ => None,
+        }
+    };
 
     if let Some(round_outcome) = round_outcome {
+        // resimulated frames must not extend an ongoing effect
+        if slow_motion_effect.is_none() {
+            commands.insert_resource(SlowMotionEffect {
+                end_time: Instant::now() + Duration::from_secs_f32(SLOW_MOTION_DURATION_SECS),
+            });
+        }
         commands.insert_resource(GameFreeze {
             end_frame: frame_count.frame + FPS, /* 1 second */
             post_freeze_action: Some(PostFreezeAction::ShowLeaderboard(round_outcome)),
@@ -1392,6 +3615,10 @@ pub fn show_leaderboard(
     primary_window_query: Query<&Window, With<PrimaryWindow>>,
     ui_root_query: Query<Entity, With<UIRoot>>,
     frame_count: Res<FrameCount>,
+    game_stats: Res<GameStats>,
+    session_time_up: Option<Res<SessionTimeUp>>,
+    player_query: Query<(Entity, &Player), Without<Dead>>,
+    mut round_finished_events: EventWriter<RoundFinishedEvent>,
 ) {
     if let Some(GameFreeze {
         end_frame: freeze_end_frame,
@@ -1399,24 +3626,63 @@ pub fn show_leaderboard(
     }) = game_freeze.as_deref()
     {
         if frame_count.frame >= *freeze_end_frame {
-            let next_action = match round_outcome {
+            leaderboard.rounds_played += 1;
+            match round_outcome {
                 RoundOutcome::Winner(player_id) => {
                     info!("Player {} won the round!", player_id.0);
-                    let player_score = leaderboard.scores.get_mut(player_id).unwrap();
-                    *player_score += 1;
-
-                    if *player_score >= leaderboard.winning_score {
-                        PostFreezeAction::ShowTournamentWinner { winner: *player_id }
-                    } else {
-                        PostFreezeAction::StartNewRound
+                    if let Some((entity, _)) = player_query
+                        .iter()
+                        .find(|(_, player)| player.id == *player_id)
+                    {
+                        commands
+                            .entity(entity)
+                            .insert(VictoryDance { blink_frame: 0 });
+                    }
+                    leaderboard.add_round_win(*player_id);
+                }
+                RoundOutcome::TeamWinner(team) => {
+                    info!("Team {team} won the round!");
+                    for (entity, _) in player_query
+                        .iter()
+                        .filter(|(_, player)| player.team == Some(*team))
+                    {
+                        commands
+                            .entity(entity)
+                            .insert(VictoryDance { blink_frame: 0 });
                     }
+                    // the team shares a single score
+                    let (&team_member, _) = leaderboard
+                        .teams
+                        .iter()
+                        .find(|(_, player_team)| *player_team == team)
+                        .unwrap();
+                    leaderboard.add_round_win(team_member);
                 }
                 RoundOutcome::Tie => {
                     info!("The round was a tie!");
-                    PostFreezeAction::StartNewRound
                 }
+            }
+            let mut next_action = match leaderboard.tournament_winner() {
+                Some(winner) => PostFreezeAction::ShowTournamentWinner { winner },
+                None => PostFreezeAction::StartNewRound,
             };
 
+            if session_time_up.is_some() && matches!(next_action, PostFreezeAction::StartNewRound) {
+                info!("The session time limit was reached, ending the tournament.");
+                next_action = PostFreezeAction::ShowTournamentWinner {
+                    winner: leaderboard.leader(),
+                };
+            }
+
+            round_finished_events.send(RoundFinishedEvent {
+                frame: frame_count.frame,
+                round_outcome: *round_outcome,
+                tournament_winner: match next_action {
+                    PostFreezeAction::ShowTournamentWinner { winner } => Some(winner),
+                    _ => None,
+                },
+            });
+
             commands
                 .entity(ui_root_query.single())
                 .with_children(|parent| {
@@ -1431,6 +3697,7 @@ pub fn show_leaderboard(
                         &fonts,
                         &leaderboard,
                         *round_outcome,
+                        &game_stats,
                     );
                 });
 
@@ -1442,17 +3709,25 @@ pub fn show_leaderboard(
     }
 }
 
+// runs during the leaderboard freeze, the player entities are despawned when the next round starts
+pub fn animate_victory_dance(mut dancing_player_query: Query<(&mut VictoryDance, &mut Sprite)>) {
+    for (mut victory_dance, mut sprite) in dancing_player_query.iter_mut() {
+        // there are no dedicated victory sprites so the penguin turns from side to side
+        sprite.flip_x = (victory_dance.blink_frame as u32 / VICTORY_FRAME_INTERVAL) % 2 == 1;
+        victory_dance.blink_frame = victory_dance.blink_frame.wrapping_add(1);
+    }
+}
+
 pub fn show_tournament_winner(
     mut session_rng: ResMut<SessionRng>,
     mut commands: Commands,
     game_freeze: Option<Res<GameFreeze>>,
     frame_count: Res<FrameCount>,
-    mut leaderboard: ResMut<Leaderboard>,
     game_textures: Res<GameTextures>,
     fonts: Res<Fonts>,
-    mut world_type: ResMut<WorldType>,
     primary_window_query: Query<&Window, With<PrimaryWindow>>,
     leaderboard_ui_content_query: Query<Entity, With<LeaderboardUIContent>>,
+    session_timer: Res<SessionTimer>,
 ) {
     if let Some(GameFreeze {
         end_frame: freeze_end_frame,
@@ -1475,21 +3750,78 @@ pub fn show_tournament_winner(
                         &game_textures,
                         &fonts,
                         *winner,
+                        session_timer.total_frames / FPS,
                     );
                 });
 
-            // setup new tournament //
+            commands.insert_resource(Confetti {
+                count: CONFETTI_COUNT,
+                rng_seed: session_rng.gen_u64(),
+            });
+
+            commands.insert_resource(GameFreeze {
+                end_frame: frame_count.frame + TOURNAMENT_WINNER_DISPLAY_FRAME_COUNT,
+                post_freeze_action: Some(PostFreezeAction::StartNewTournament),
+            })
+        }
+    }
+}
+
+pub fn start_new_tournament(
+    mut session_rng: ResMut<SessionRng>,
+    mut commands: Commands,
+    game_freeze: Option<Res<GameFreeze>>,
+    frame_count: Res<FrameCount>,
+    mut leaderboard: ResMut<Leaderboard>,
+    mut game_stats: ResMut<GameStats>,
+    mut world_type: ResMut<WorldType>,
+    session_time_up: Option<Res<SessionTimeUp>>,
+) {
+    if let Some(GameFreeze {
+        end_frame: freeze_end_frame,
+        post_freeze_action: Some(PostFreezeAction::StartNewTournament),
+    }) = game_freeze.as_deref()
+    {
+        if frame_count.frame >= *freeze_end_frame {
+            if session_time_up.is_some() {
+                // the session is over, the tournament winner stays on display
+                commands.insert_resource(GameFreeze {
+                    end_frame: u32::MAX,
+                    post_freeze_action: None,
+                });
+                return;
+            }
 
             // reset the leaderboard
-            for (_, score) in &mut leaderboard.scores {
-                *score = 0;
+            match leaderboard.score_mode {
+                ScoreMode::ResetBetweenTournaments => {
+                    for (_, score) in &mut leaderboard.scores {
+                        *score = 0;
+                    }
+                }
+                ScoreMode::Cumulative => {
+                    leaderboard.tournament_start_scores = leaderboard.scores.clone();
+                }
+            }
+            leaderboard.rounds_played = 0;
+
+            for (player_id, longest_chain) in game_stats.longest_chain.drain() {
+                info!(
+                    "Player {} longest chain: {longest_chain} bombs",
+                    player_id.0
+                );
+            }
+            for (player_id, tiles_walked) in game_stats.tiles_walked.drain() {
+                info!("Player {} distance: {tiles_walked} tiles", player_id.0);
             }
+            game_stats.items_collected.clear();
 
             // choose a world for the next tournament
             *world_type = world_type.next_random(&mut session_rng);
 
+            // the new round starts right away, the winner display already gave the players a pause
             commands.insert_resource(GameFreeze {
-                end_frame: frame_count.frame + TOURNAMENT_WINNER_DISPLAY_FRAME_COUNT,
+                end_frame: frame_count.frame,
                 post_freeze_action: Some(PostFreezeAction::StartNewRound),
             })
         }
@@ -1508,6 +3840,10 @@ pub fn start_new_round(
     game_textures: ResMut<GameTextures>,
     fonts: Res<Fonts>,
     hud_colors: Res<HUDColors>,
+    mut current_round_modifier: ResMut<CurrentRoundModifier>,
+    game_config: Res<GameConfig>,
+    leaderboard: Res<Leaderboard>,
+    primary_window_query: Query<&Window, With<PrimaryWindow>>,
 ) {
     if let Some(GameFreeze {
         end_frame: freeze_end_frame,
@@ -1519,6 +3855,24 @@ pub fn start_new_round(
             for e in teardown_entities_query.iter() {
                 commands.entity(e).despawn();
             }
+            commands.remove_resource::<Confetti>();
+            commands.insert_resource(WallOfDeathConsumed::default());
+
+            // choose the special rules for this round
+            let round_modifier = RoundModifier::random(&mut session_rng);
+            info!(
+                "[frame:{}] Round modifier: {round_modifier:?}",
+                frame_count.frame
+            );
+            *current_round_modifier = CurrentRoundModifier(round_modifier);
+            if let Some(name) = round_modifier.name() {
+                setup_freeze_message_display(
+                    &mut commands,
+                    primary_window_query.single(),
+                    &fonts,
+                    name,
+                );
+            }
 
             let round_start_frame = frame_count.frame + GAME_START_FREEZE_FRAME_COUNT;
             setup_round(
@@ -1529,7 +3883,9 @@ pub fn start_new_round(
                 &game_textures,
                 &fonts,
                 &hud_colors,
+                &game_config,
                 matchbox_config.number_of_players,
+                &leaderboard.teams,
                 round_start_frame,
             );
             commands.insert_resource(GameFreeze {
@@ -1544,6 +3900,7 @@ pub fn finish_actionless_game_freeze(
     mut commands: Commands,
     game_freeze: Option<Res<GameFreeze>>,
     frame_count: Res<FrameCount>,
+    freeze_message_query: Query<Entity, With<FreezeMessageDisplay>>,
 ) {
     if let Some(GameFreeze {
         end_frame: freeze_end_frame,
@@ -1552,6 +3909,362 @@ pub fn finish_actionless_game_freeze(
     {
         if frame_count.frame >= *freeze_end_frame {
             commands.remove_resource::<GameFreeze>();
+
+            for entity in freeze_message_query.iter() {
+                commands.entity(entity).despawn_recursive();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{constants::PLAYER_MOVE_FRAME_INTERVAL, resources::MapTextures};
+
+    // the textures are never loaded in tests
+    impl Default for GameTextures {
+        fn default() -> Self {
+            Self {
+                penguin_variants: vec![],
+                bomb: Handle::default(),
+                fire: Handle::default(),
+                map_textures: WorldType::LIST
+                    .into_iter()
+                    .map(|world_type| {
+                        (
+                            world_type,
+                            MapTextures {
+                                empty: Handle::default(),
+                                wall: Handle::default(),
+                                destructible_wall: Handle::default(),
+                                burning_wall: Handle::default(),
+                                cracked_ice: Handle::default(),
+                            },
+                        )
+                    })
+                    .collect(),
+                bombs_up: Handle::default(),
+                range_up: Handle::default(),
+                bomb_push: Handle::default(),
+                speed_up: Handle::default(),
+                bomb_down: Handle::default(),
+                range_down: Handle::default(),
+                skull: Handle::default(),
+                remote_bomb: Handle::default(),
+                pierce: Handle::default(),
+                magnet: Handle::default(),
+                burning_item: Handle::default(),
+                trophy: Handle::default(),
+            }
+        }
+    }
+
+    // an empty 11x15 world on its first frame
+    fn setup_test_world() -> World {
+        let mut world = World::new();
+        world.insert_resource(GameTextures::default());
+        world.insert_resource(WorldType::GrassWorld);
+        world.insert_resource(MapSize {
+            rows: 11,
+            columns: 15,
+        });
+        world.insert_resource(FrameCount { frame: 0 });
+        world.init_resource::<RollbackOrdered>();
+        world
+    }
+
+    // a bomb far from exploding, moving on every frame when given a direction
+    fn spawn_bomb(
+        commands: &mut Commands,
+        position: Position,
+        moving: Option<Direction>,
+    ) -> Entity {
+        let mut entity_commands = commands.spawn((
+            Bomb {
+                owner: None,
+                range: 1,
+                expiration_frame: 100,
+                is_remote: false,
+                pierces_walls: false,
+            },
+            position,
+            Transform::default(),
+            Solid,
+        ));
+        entity_commands.add_rollback();
+        if let Some(direction) = moving {
+            entity_commands.insert(Moving {
+                direction,
+                next_move_frame: 0,
+                frame_interval: 1,
+            });
+        }
+        entity_commands.id()
+    }
+
+    #[test]
+    fn test_wall_of_death_done_sent_once() {
+        let mut world = setup_test_world();
+        let map_size = *world.resource::<MapSize>();
+        world.insert_resource(GameConfig::default());
+        // the wall is already at its last position, so the next step finishes it
+        world.insert_resource(WallOfDeath::Active {
+            position: Position {
+                y: map_size.rows - 3,
+                x: 3,
+            },
+            direction: Direction::Left,
+            next_step_frame: 0,
+        });
+        world.init_resource::<Events<WallOfDeathDone>>();
+        world.init_resource::<WallOfDeathConsumed>();
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems(wall_of_death_update);
+        for frame in 0..10 {
+            world.resource_mut::<FrameCount>().frame = frame;
+            schedule.run(&mut world);
+        }
+
+        assert!(matches!(
+            *world.resource::<WallOfDeath>(),
+            WallOfDeath::Done
+        ));
+        let events = world.resource::<Events<WallOfDeathDone>>();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events.iter_current_update_events().next().unwrap().frame, 0);
+    }
+
+    #[test]
+    fn test_wall_of_death_pauses_during_freeze() {
+        let step_interval = GameConfig::default().wall_of_death_step_interval;
+
+        let mut world = setup_test_world();
+        world.insert_resource(GameConfig::default());
+        world.insert_resource(WallOfDeath::Dormant {
+            activation_frame: 0,
+        });
+        world.init_resource::<Events<WallOfDeathDone>>();
+        world.init_resource::<WallOfDeathConsumed>();
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems(wall_of_death_update);
+        let mut run_frame = |world: &mut World, frame: u32| {
+            world.resource_mut::<FrameCount>().frame = frame;
+            schedule.run(world);
+        };
+        let wall_position = |world: &World| match *world.resource::<WallOfDeath>() {
+            WallOfDeath::Active { position, .. } => position,
+            _ => panic!("the wall of death should be active"),
+        };
+
+        // (1) activates and makes the first step
+        run_frame(&mut world, 0);
+        let first_position = wall_position(&world);
+        assert_eq!(first_position, Position { y: 9, x: 1 });
+
+        // (2) freeze for longer than a step interval
+        let freeze_end_frame = 10;
+        assert!(freeze_end_frame > step_interval);
+        world.insert_resource(GameFreeze {
+            end_frame: freeze_end_frame,
+            post_freeze_action: None,
+        });
+        for frame in 1..=freeze_end_frame {
+            run_frame(&mut world, frame);
+        }
+
+        // (3) the wall didn't move during the freeze
+        assert_eq!(wall_position(&world), first_position);
+
+        // (4) a single step right after the freeze, then the regular interval
+        world.remove_resource::<GameFreeze>();
+        run_frame(&mut world, freeze_end_frame + 1);
+        let second_position = wall_position(&world);
+        assert_eq!(second_position, Position { y: 8, x: 1 });
+        for frame in freeze_end_frame + 2..freeze_end_frame + 1 + step_interval {
+            run_frame(&mut world, frame);
+            assert_eq!(wall_position(&world), second_position);
+        }
+        run_frame(&mut world, freeze_end_frame + 1 + step_interval);
+        assert_eq!(wall_position(&world), Position { y: 7, x: 1 });
+
+        assert_eq!(world.query::<&Wall>().iter(&world).count(), 3);
+    }
+
+    #[test]
+    fn test_bomb_move_head_on_bombs_stop() {
+        let mut world = setup_test_world();
+
+        // two adjacent bombs pushed into each other, each waits for the other one to move away
+        let mut setup_schedule = Schedule::default();
+        setup_schedule.add_systems(|mut commands: Commands| {
+            for (x, direction) in [(3, Direction::Right), (4, Direction::Left)] {
+                spawn_bomb(&mut commands, Position { y: 1, x }, Some(direction));
+            }
+        });
+        setup_schedule.run(&mut world);
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems(bomb_move);
+        schedule.run(&mut world);
+
+        let positions = world
+            .query_filtered::<&Position, With<Bomb>>()
+            .iter(&world)
+            .copied()
+            .sorted()
+            .collect_vec();
+        assert_eq!(
+            positions,
+            vec![Position { y: 1, x: 3 }, Position { y: 1, x: 4 }]
+        );
+    }
+
+    #[test]
+    fn test_bomb_move_stops_after_max_iterations() {
+        let mut world = setup_test_world();
+        // caps the loop at two iterations, the bombs are placed past the map as only the cap matters here
+        world.insert_resource(MapSize {
+            rows: 1,
+            columns: 2,
+        });
+
+        // a line of bombs moving right, the front one is checked last so every iteration moves a single bomb
+        let mut setup_schedule = Schedule::default();
+        setup_schedule.add_systems(|mut commands: Commands| {
+            for x in 1..=4 {
+                spawn_bomb(&mut commands, Position { y: 1, x }, Some(Direction::Right));
+            }
+        });
+        setup_schedule.run(&mut world);
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems(bomb_move);
+        schedule.run(&mut world);
+
+        // without the cap the last two bombs would have moved as well
+        let positions = world
+            .query_filtered::<&Position, With<Bomb>>()
+            .iter(&world)
+            .map(|position| position.x)
+            .sorted()
+            .collect_vec();
+        assert_eq!(positions, vec![1, 2, 4, 5]);
+    }
+
+    #[test]
+    fn test_speed_up_lowers_move_interval() {
+        let mut world = setup_test_world();
+        world.init_resource::<WorldConfigs>();
+        world.insert_resource(SessionRng::new(0));
+        world.init_resource::<GameStats>();
+
+        let position = Position { y: 1, x: 1 };
+        let player_entity = world
+            .spawn((
+                Player {
+                    id: PlayerID(0),
+                    can_push_bombs: false,
+                    magnet_radius: 0,
+                    last_move_direction: None,
+                    move_cooldown_frames: PLAYER_MOVE_FRAME_INTERVAL,
+                    next_move_frame: 0,
+                    pending_move: None,
+                    stunned_until_frame: 0,
+                    team: None,
+                },
+                position,
+                BombSatchel {
+                    bombs_available: 1,
+                    bomb_range: 1,
+                    has_remote_detonator: false,
+                    has_pierce: false,
+                },
+            ))
+            .id();
+        // drop a speed up under the player before every pick up
+        let mut schedule = Schedule::default();
+        schedule.add_systems(
+            (
+                move |mut commands: Commands| {
+                    commands
+                        .spawn((Item::SpeedUp, position, Handle::<Image>::default()))
+                        .add_rollback();
+                },
+                apply_deferred,
+                pick_up_item,
+            )
+                .chain(),
+        );
+        let mut intervals = vec![];
+        for _ in 0..PLAYER_MOVE_FRAME_INTERVAL {
+            schedule.run(&mut world);
+            intervals.push(
+                world
+                    .get::<Player>(player_entity)
+                    .unwrap()
+                    .move_cooldown_frames,
+            );
         }
+
+        // every speed up makes a difference until the minimum is reached
+        assert!(PLAYER_MOVE_FRAME_INTERVAL > MIN_PLAYER_MOVE_FRAME_INTERVAL);
+        assert_eq!(intervals[0], PLAYER_MOVE_FRAME_INTERVAL - 1);
+        assert_eq!(*intervals.last().unwrap(), MIN_PLAYER_MOVE_FRAME_INTERVAL);
+    }
+
+    #[test]
+    fn test_explode_bombs_pierces_one_destructible_wall() {
+        let mut world = setup_test_world();
+        world.init_resource::<WorldConfigs>();
+        world.init_resource::<GameStats>();
+        world.init_resource::<Events<BombExplodedEvent>>();
+
+        // a piercing bomb with two destructible walls to its right
+        let mut setup_schedule = Schedule::default();
+        setup_schedule.add_systems(|mut commands: Commands| {
+            let bomb = spawn_bomb(&mut commands, Position { y: 1, x: 1 }, None);
+            commands.entity(bomb).insert((
+                Bomb {
+                    owner: None,
+                    range: 5,
+                    expiration_frame: 0,
+                    is_remote: false,
+                    pierces_walls: true,
+                },
+                ChainDepth::default(),
+                ChainInitiator(None),
+            ));
+            for x in [3, 5] {
+                commands
+                    .spawn((
+                        Wall,
+                        Destructible,
+                        Solid,
+                        Position { y: 1, x },
+                        Handle::<Image>::default(),
+                    ))
+                    .add_rollback();
+            }
+        });
+        setup_schedule.run(&mut world);
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems(explode_bombs);
+        schedule.run(&mut world);
+
+        let fire_positions: HashSet<Position> = world
+            .query_filtered::<&Position, With<Fire>>()
+            .iter(&world)
+            .copied()
+            .collect();
+        assert!(fire_positions.contains(&Position { y: 1, x: 2 }));
+        // the first wall is pierced
+        assert!(fire_positions.contains(&Position { y: 1, x: 4 }));
+        // the second one stops the fire
+        assert!(!fire_positions.contains(&Position { y: 1, x: 6 }));
+        assert_eq!(world.query::<&Crumbling>().iter(&world).count(), 2);
     }
 }
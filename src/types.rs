@@ -1,4 +1,8 @@
-use std::time::Duration;
+use std::{
+    hash::{Hash, Hasher},
+    str::FromStr,
+    time::Duration,
+};
 
 use bevy::{
     prelude::Color,
@@ -6,12 +10,18 @@ use bevy::{
     ui::BackgroundColor,
 };
 use bevy_ggrs::ggrs::Config;
+#[cfg(debug_assertions)]
+use bevy_ggrs::ggrs::{Message, NonBlockingSocket};
+#[cfg(debug_assertions)]
+use bevy_matchbox::matchbox_socket::WebRtcChannel;
 use bevy_matchbox::prelude::PeerId;
 use bytemuck::{Pod, Zeroable};
 
+use crate::resources::SessionRng;
+
 #[repr(C)]
 #[derive(Copy, Clone, PartialEq, Eq, Pod, Zeroable)]
-pub struct PlayerInput(pub u8);
+pub struct PlayerInput(pub u16);
 
 #[derive(Debug)]
 pub struct GgrsConfig;
@@ -22,6 +32,28 @@ impl Config for GgrsConfig {
     type Address = PeerId;
 }
 
+// randomly drops outgoing GGRS packets to test rollbacks without a bad network, never part of release builds
+#[cfg(debug_assertions)]
+pub struct LossyChannel {
+    pub channel: WebRtcChannel,
+    pub loss_percentage: u8,
+}
+
+#[cfg(debug_assertions)]
+impl NonBlockingSocket<PeerId> for LossyChannel {
+    fn send_to(&mut self, msg: &Message, addr: &PeerId) {
+        if rand::random::<f32>() < self.loss_percentage as f32 / 100.0 {
+            return;
+        }
+
+        self.channel.send_to(msg, addr);
+    }
+
+    fn receive_all_messages(&mut self) -> Vec<(PeerId, Message)> {
+        self.channel.receive_all_messages()
+    }
+}
+
 pub struct ICEServerConfig {
     pub url: String,
     pub username: Option<String>,
@@ -54,20 +86,173 @@ pub enum Direction {
     Down,
 }
 
+impl Hash for Direction {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let value: u8 = match self {
+            Self::Left => 0,
+            Self::Right => 1,
+            Self::Up => 2,
+            Self::Down => 3,
+        };
+        value.hash(state);
+    }
+}
+
 impl Direction {
     pub const LIST: [Self; 4] = [Self::Right, Self::Left, Self::Up, Self::Down];
+
+    pub fn opposite(&self) -> Self {
+        match self {
+            Self::Left => Self::Right,
+            Self::Right => Self::Left,
+            Self::Up => Self::Down,
+            Self::Down => Self::Up,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MapLayout {
+    #[default]
+    Random,
+    Maze,
+}
+
+impl FromStr for MapLayout {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "random" => Ok(Self::Random),
+            "maze" => Ok(Self::Maze),
+            _ => Err(format!("unknown map layout: {s}")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MapSymmetry {
+    #[default]
+    None,
+    Rotational180,
+}
+
+impl FromStr for MapSymmetry {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Self::None),
+            "180" => Ok(Self::Rotational180),
+            _ => Err(format!("unknown map symmetry: {s}")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TournamentFormat {
+    // the first player to win N rounds wins the tournament
+    FirstToN(u8),
+    // the player with the most wins after N rounds wins the tournament, extra rounds are played while the lead is shared
+    BestOfN(u8),
+}
+
+impl FromStr for TournamentFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse = |v: &str| match v.parse::<u8>() {
+            Ok(0) => Err(format!("{v}: the round count must be positive")),
+            Ok(n) => Ok(n),
+            Err(e) => Err(format!("{v}: {e}")),
+        };
+
+        if let Some(n) = s.strip_prefix("first-to-") {
+            Ok(Self::FirstToN(parse(n)?))
+        } else if let Some(n) = s.strip_prefix("best-of-") {
+            let n = parse(n)?;
+            if n % 2 == 0 {
+                return Err(format!("best-of-N requires an odd N, got: {n}"));
+            }
+            Ok(Self::BestOfN(n))
+        } else {
+            Err(format!("expected first-to-N or best-of-N, got: {s}"))
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ScoreMode {
+    #[default]
+    ResetBetweenTournaments,
+    // round wins carry over to the following tournaments
+    Cumulative,
 }
 
 #[derive(Clone, Copy)]
 pub enum RoundOutcome {
     Tie,
     Winner(PlayerID),
+    // the last players standing were all on this team
+    TeamWinner(u8),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum RoundModifier {
+    GiantBombs,
+    SpeedRound,
+    NoItems,
+    FuseRush,
+    Normal,
+}
+
+impl RoundModifier {
+    pub fn random(rng: &mut SessionRng) -> Self {
+        match rng.gen_range(0, 5) {
+            0 => Self::GiantBombs,
+            1 => Self::SpeedRound,
+            2 => Self::NoItems,
+            3 => Self::FuseRush,
+            4 => Self::Normal,
+            _ => unreachable!(),
+        }
+    }
+
+    pub fn name(&self) -> Option<&'static str> {
+        match self {
+            Self::GiantBombs => Some("GIANT BOMBS!"),
+            Self::SpeedRound => Some("SPEED ROUND!"),
+            Self::NoItems => Some("NO ITEMS!"),
+            Self::FuseRush => Some("FUSE RUSH!"),
+            Self::Normal => None,
+        }
+    }
+}
+
+// negative effects of picking up a skull
+#[derive(Debug, Clone, Copy)]
+pub enum SkullEffect {
+    HalveRange,
+    LosePush,
+    Stun,
+}
+
+impl SkullEffect {
+    pub fn random(rng: &mut SessionRng) -> Self {
+        match rng.gen_range(0, 3) {
+            0 => Self::HalveRange,
+            1 => Self::LosePush,
+            2 => Self::Stun,
+            _ => unreachable!(),
+        }
+    }
 }
 
 #[derive(Clone, Copy)]
 pub enum PostFreezeAction {
     ShowLeaderboard(RoundOutcome),
     ShowTournamentWinner { winner: PlayerID },
+    StartNewTournament,
     StartNewRound,
 }
 
@@ -79,6 +264,9 @@ pub enum CooldownState {
 pub struct Cooldown {
     state: CooldownState,
     duration: Duration,
+    repeating: bool,
+    just_fired: bool,
+    paused: bool,
 }
 
 impl Cooldown {
@@ -86,6 +274,17 @@ impl Cooldown {
         Cooldown {
             state: CooldownState::Ready,
             duration: Duration::from_secs_f32(duration),
+            repeating: false,
+            just_fired: false,
+            paused: false,
+        }
+    }
+
+    // fires on the first tick and then every time the duration elapses
+    pub fn from_seconds_repeating(duration: f32) -> Self {
+        Cooldown {
+            repeating: true,
+            ..Self::from_seconds(duration)
         }
     }
 
@@ -102,17 +301,63 @@ impl Cooldown {
     }
 
     pub fn tick(&mut self, delta: Duration) {
+        self.just_fired = false;
+        if self.paused {
+            return;
+        }
+
         match self.state {
-            CooldownState::Ready => (),
+            CooldownState::Ready => {
+                if self.repeating {
+                    self.state = CooldownState::CoolingDown(Timer::from_seconds(
+                        self.duration.as_secs_f32(),
+                        TimerMode::Repeating,
+                    ));
+                    self.just_fired = true;
+                }
+            }
             CooldownState::CoolingDown(ref mut timer) => {
                 timer.tick(delta);
                 if timer.finished() {
-                    self.state = CooldownState::Ready;
+                    self.just_fired = true;
+                    if !self.repeating {
+                        self.state = CooldownState::Ready;
+                    }
                 }
             }
         };
     }
 
+    pub fn just_fired(&self) -> bool {
+        self.just_fired
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    #[allow(dead_code)]
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    #[allow(dead_code)]
+    pub fn is_cooling_down(&self) -> bool {
+        matches!(self.state, CooldownState::CoolingDown(_))
+    }
+
+    #[allow(dead_code)]
+    pub fn remaining(&self) -> Option<Duration> {
+        match self.state {
+            CooldownState::Ready => None,
+            CooldownState::CoolingDown(ref timer) => Some(timer.remaining()),
+        }
+    }
+
     pub fn reset(&mut self) {
         self.state = CooldownState::Ready;
     }
@@ -127,6 +372,22 @@ impl Cooldown {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_tournament_format_from_str() {
+        assert_eq!(
+            "first-to-3".parse::<TournamentFormat>(),
+            Ok(TournamentFormat::FirstToN(3))
+        );
+        assert_eq!(
+            "best-of-5".parse::<TournamentFormat>(),
+            Ok(TournamentFormat::BestOfN(5))
+        );
+        assert!("best-of-4".parse::<TournamentFormat>().is_err());
+        assert!("first-to-0".parse::<TournamentFormat>().is_err());
+        assert!("first-to-x".parse::<TournamentFormat>().is_err());
+        assert!("3".parse::<TournamentFormat>().is_err());
+    }
+
     #[test]
     fn test_cooldown() {
         let mut cooldown = Cooldown::from_seconds(0.5);
@@ -146,4 +407,101 @@ mod tests {
         assert!(cooldown.trigger());
         assert!(!cooldown.trigger());
     }
+
+    #[test]
+    fn test_repeating_cooldown() {
+        let mut cooldown = Cooldown::from_seconds_repeating(0.5);
+
+        cooldown.tick(Duration::ZERO);
+        assert!(cooldown.just_fired());
+
+        cooldown.tick(Duration::from_secs_f32(0.3));
+        assert!(!cooldown.just_fired());
+
+        cooldown.tick(Duration::from_secs_f32(0.2));
+        assert!(cooldown.just_fired());
+
+        // restarts instead of becoming ready
+        cooldown.tick(Duration::from_secs_f32(0.3));
+        assert!(!cooldown.just_fired());
+
+        cooldown.tick(Duration::from_secs_f32(0.2));
+        assert!(cooldown.just_fired());
+
+        // fires on the first tick after a reset
+        cooldown.tick(Duration::from_secs_f32(0.1));
+        cooldown.reset();
+        cooldown.tick(Duration::from_secs_f32(0.1));
+        assert!(cooldown.just_fired());
+        cooldown.tick(Duration::from_secs_f32(0.1));
+        assert!(!cooldown.just_fired());
+    }
+
+    #[test]
+    fn test_cooldown_pause() {
+        let mut cooldown = Cooldown::from_seconds(0.5);
+        assert!(cooldown.trigger());
+
+        cooldown.pause();
+        assert!(cooldown.is_paused());
+        cooldown.tick(Duration::from_secs_f32(1.0));
+        assert!(!cooldown.trigger());
+        assert_eq!(cooldown.remaining(), Some(Duration::from_millis(500)));
+
+        cooldown.resume();
+        assert!(!cooldown.is_paused());
+        cooldown.tick(Duration::from_secs_f32(0.3));
+        assert!(!cooldown.trigger());
+
+        cooldown.tick(Duration::from_secs_f32(0.2));
+        assert!(cooldown.trigger());
+    }
+
+    #[test]
+    fn test_cooldown_remaining() {
+        let mut cooldown = Cooldown::from_seconds(0.5);
+        assert_eq!(cooldown.remaining(), None);
+
+        assert!(cooldown.trigger());
+        assert_eq!(cooldown.remaining(), Some(Duration::from_millis(500)));
+
+        cooldown.tick(Duration::from_millis(300));
+        assert_eq!(cooldown.remaining(), Some(Duration::from_millis(200)));
+
+        cooldown.tick(Duration::from_millis(200));
+        assert_eq!(cooldown.remaining(), None);
+    }
+
+    #[test]
+    fn test_cooldown_is_cooling_down() {
+        let mut cooldown = Cooldown::from_seconds(0.5);
+        assert!(!cooldown.is_cooling_down());
+
+        // ticking a ready cooldown has no effect
+        cooldown.tick(Duration::from_secs_f32(1.0));
+        assert!(!cooldown.is_cooling_down());
+
+        assert!(cooldown.trigger());
+        assert!(cooldown.is_cooling_down());
+
+        // just before the boundary
+        cooldown.tick(Duration::from_millis(499));
+        assert!(cooldown.is_cooling_down());
+        assert_eq!(cooldown.remaining(), Some(Duration::from_millis(1)));
+
+        // exactly at the boundary
+        cooldown.tick(Duration::from_millis(1));
+        assert!(!cooldown.is_cooling_down());
+        assert_eq!(cooldown.remaining(), None);
+
+        // overshooting the boundary
+        assert!(cooldown.trigger());
+        cooldown.tick(Duration::from_secs_f32(2.0));
+        assert!(!cooldown.is_cooling_down());
+        assert_eq!(cooldown.remaining(), None);
+
+        cooldown.trigger();
+        cooldown.reset();
+        assert!(!cooldown.is_cooling_down());
+    }
 }
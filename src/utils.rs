@@ -1,33 +1,55 @@
+use std::{
+    collections::{hash_map::DefaultHasher, VecDeque},
+    hash::{Hash, Hasher},
+};
+
 use base64::{engine::general_purpose::STANDARD_NO_PAD, Engine};
+use bevy::log::warn;
 use bevy::{
     asset::Handle,
     ecs::entity::Entity,
-    prelude::{BuildChildren, ChildBuilder, Commands, NodeBundle, TextBundle, Transform, Vec2},
+    prelude::{
+        BuildChildren, ButtonBundle, Camera2dBundle, ChildBuilder, Commands, NodeBundle,
+        Text2dBundle, TextBundle, Transform, Vec2, Visibility,
+    },
     render::{color::Color, texture::Image},
     sprite::{Sprite, SpriteBundle},
-    text::{Text, TextStyle},
-    ui::{node_bundles::ImageBundle, PositionType, Style, UiRect, Val},
-    utils::HashSet,
+    text::{Text, TextAlignment, TextStyle},
+    ui::{
+        node_bundles::ImageBundle, AlignItems, JustifyContent, PositionType, Style, UiRect, Val,
+        ZIndex,
+    },
+    utils::{HashMap, HashSet},
     window::Window,
 };
-use bevy_ggrs::AddRollbackCommandExtension;
+use bevy_ggrs::{AddRollbackCommandExtension, Session};
+use bevy_matchbox::matchbox_socket::PeerId;
 use itertools::Itertools;
+use sha2::{Digest, Sha256};
 
 use crate::{
     components::{
-        BombSatchel, BurningItem, Destructible, FullscreenMessageText, GameTimerDisplay, HUDRoot,
-        Item, LeaderboardUIContent, LeaderboardUIRoot, NetworkStatsDisplay, Player, PlayerPortrait,
-        PlayerPortraitDisplay, Position, Solid, UIComponent, UIRoot, Wall,
+        BombCountOverlay, BombSatchel, BurningItem, CameraShake, CrackableIce, Destructible,
+        FadeIn, FreezeMessageDisplay, FullscreenMessageText, GameTimerDisplay, HUDRoot, Item,
+        LeaderboardUIContent, LeaderboardUIRoot, LobbyConnectedCounter, LobbyPortrait,
+        NetworkStatsDisplay, Player, PlayerHP, PlayerHealthBar, PlayerPortrait,
+        PlayerPortraitDisplay, Position, RetryButton, RoomUrlDisplay, Solid, UIComponent, UIRoot,
+        Wall, WallCountDisplay, WaterTile,
     },
     constants::{
-        COLORS, DESTRUCTIBLE_WALL_Z_LAYER, FPS, HUD_HEIGHT, ITEM_Z_LAYER, PIXEL_SCALE,
-        PLAYER_Z_LAYER, ROUND_DURATION_SECS, TILE_HEIGHT, TILE_WIDTH, WALL_Z_LAYER,
+        BOMB_OWNER_TINT_STRENGTH, COLORS, CRACKABLE_ICE_DENSITY, CRACKABLE_ICE_HP,
+        CRACKABLE_ICE_Z_LAYER, DESTRUCTIBLE_WALL_Z_LAYER, FPS, HUD_HEIGHT, ITEM_Z_LAYER,
+        PIXEL_SCALE, PLAYER_COLOR_INDICES, PLAYER_MOVE_FRAME_INTERVAL, PLAYER_Z_LAYER,
+        SEED_COMMITMENT_LENGTH, TEAM_COLOR_INDICES, TILE_HEIGHT, TILE_WIDTH, WALL_Z_LAYER,
+        WATER_TILE_DENSITY, WATER_Z_LAYER,
     },
     resources::{
-        Fonts, GameEndFrame, GameTextures, HUDColors, Leaderboard, MapSize, SessionRng,
-        WallOfDeath, WorldType,
+        CurrentTip, Fonts, FrameCount, GameConfig, GameEndFrame, GameStats, GameTextures,
+        HUDColors, Leaderboard, LootTable, MapSize, OvertimeActive, SessionRng, WallOfDeath,
+        WorldType,
     },
-    types::{Direction, PlayerID, RoundOutcome},
+    types::{Direction, MapLayout, MapSymmetry, PlayerID, RoundOutcome, ScoreMode},
+    GgrsConfig,
 };
 
 pub fn get_x(x: u8) -> f32 {
@@ -38,13 +60,136 @@ pub fn get_y(y: u8) -> f32 {
     -(TILE_HEIGHT as f32 / 2.0 + (y as u32 * TILE_HEIGHT) as f32)
 }
 
+// the positions reached by the fire of a bomb, including its own, solids stop the fire
+pub fn blast_positions(
+    bomb_position: Position,
+    range: u8,
+    map_size: MapSize,
+    solids: &HashSet<Position>,
+) -> Vec<Position> {
+    let mut positions = vec![bomb_position];
+    for direction in Direction::LIST {
+        for position in bomb_position
+            .iter_in_direction(direction, range as usize)
+            .take_while(|p| p.in_bounds(map_size))
+        {
+            if solids.contains(&position) {
+                break;
+            }
+            positions.push(position);
+        }
+    }
+    positions
+}
+
+pub fn player_id_to_color_index(player_id: PlayerID) -> usize {
+    PLAYER_COLOR_INDICES[player_id.0 as usize % PLAYER_COLOR_INDICES.len()]
+}
+
+// linear interpolation of the color channels, the alpha of the base color is kept
+pub fn tint_toward(base: Color, tint: Color, strength: f32) -> Color {
+    let lerp = |a: f32, b: f32| a + (b - a) * strength;
+    Color::rgba(
+        lerp(base.r(), tint.r()),
+        lerp(base.g(), tint.g()),
+        lerp(base.b(), tint.b()),
+        base.a(),
+    )
+}
+
+pub fn bomb_tint(owner: Option<PlayerID>) -> Color {
+    match owner {
+        Some(player_id) => tint_toward(
+            Color::default(),
+            COLORS[player_id_to_color_index(player_id)].into(),
+            BOMB_OWNER_TINT_STRENGTH,
+        ),
+        None => Color::default(),
+    }
+}
+
+// resizes the window to fit the map and spawns the main camera
+pub fn setup_map_view(commands: &mut Commands, window: &mut Window, map_size: MapSize) {
+    window.resolution.set(
+        (map_size.columns as u32 * TILE_WIDTH) as f32,
+        (HUD_HEIGHT + map_size.rows as u32 * TILE_HEIGHT) as f32,
+    );
+
+    let camera_transform = Transform::from_xyz(
+        ((map_size.columns as u32 * TILE_WIDTH) as f32) / 2.0,
+        -((map_size.rows as u32 * TILE_HEIGHT - HUD_HEIGHT) as f32 / 2.0),
+        999.9,
+    );
+    commands.spawn((
+        Camera2dBundle {
+            transform: camera_transform,
+            ..Default::default()
+        },
+        CameraShake {
+            origin: camera_transform.translation,
+            intensity: 0.0,
+        },
+    ));
+}
+
+// the last `FrameCount` frame that won't be resimulated anymore, if there is one yet
+pub fn confirmed_frame(session: &Session<GgrsConfig>, frame_count: &FrameCount) -> Option<u32> {
+    let confirmed_ggrs_frame = match session {
+        Session::P2P(s) => s.confirmed_frame(),
+        // the training mode sync test session never resimulates
+        Session::SyncTest(s) => s.current_frame() - 1,
+        // spectators only ever advance with confirmed inputs
+        Session::Spectator(_) => return Some(frame_count.frame),
+    };
+    // GGRS counts frames from 0 while `increase_frame_system` already counts the first one as 1
+    u32::try_from(confirmed_ggrs_frame + 1).ok()
+}
+
+// the same for every player that sees the same lobby members
+pub fn lobby_round(members: impl Iterator<Item = PeerId>) -> u64 {
+    let mut hasher = Sha256::new();
+    for member in members.sorted_by_key(|member| member.0) {
+        hasher.update(member.0.as_bytes());
+    }
+    let hash = hasher.finalize();
+    u64::from_be_bytes(hash[..8].try_into().unwrap())
+}
+
+// peers only play together with the same settings and teams, the lobby compares these
+pub fn config_fingerprint(
+    game_config: &GameConfig,
+    loot_table: &LootTable,
+    team_assignments: &Option<Vec<u8>>,
+) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    game_config.hash(&mut hasher);
+    loot_table.hash(&mut hasher);
+    match team_assignments {
+        Some(team_assignments) => {
+            1u8.hash(&mut hasher);
+            for team in team_assignments {
+                team.hash(&mut hasher);
+            }
+        }
+        None => 0u8.hash(&mut hasher),
+    }
+    hasher.finish()
+}
+
+pub fn seed_commitment(seed: u64) -> [u8; SEED_COMMITMENT_LENGTH] {
+    let hash = Sha256::digest(seed.to_be_bytes());
+    let mut commitment = [0; SEED_COMMITMENT_LENGTH];
+    commitment.copy_from_slice(&hash[..SEED_COMMITMENT_LENGTH]);
+    commitment
+}
+
 pub fn decode(input: &str) -> String {
     String::from_utf8(STANDARD_NO_PAD.decode(input).unwrap()).unwrap()
 }
 
 pub fn shuffle<T>(elements: &mut [T], rng: &mut SessionRng) {
     for i in (1..elements.len()).rev() {
-        elements.swap(i, (rng.gen_u64() % (i as u64 + 1)) as usize);
+        elements.swap(i, rng.gen_range(0, i as u64 + 1) as usize);
     }
 }
 
@@ -53,20 +198,27 @@ pub fn setup_fullscreen_message_display(
     window: &Window,
     fonts: &Fonts,
     message: &str,
+    current_frame: u32,
 ) {
     let center_y = window.height() / 2.0 - (4 * PIXEL_SCALE) as f32 /* accounting for the get ready text */;
     let center_x = window.width() / 2.0;
 
     commands
-        .spawn((NodeBundle {
-            style: Style {
-                width: Val::Percent(100.0),
-                height: Val::Percent(100.0),
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    ..Default::default()
+                },
+                background_color: Color::from(COLORS[0]).with_a(0.0).into(),
                 ..Default::default()
             },
-            background_color: COLORS[0].into(),
-            ..Default::default()
-        },))
+            FadeIn {
+                start_frame: current_frame,
+                duration_frames: FPS / 2,
+            },
+        ))
         .with_children(|parent| {
             parent.spawn((
                 TextBundle {
@@ -75,7 +227,7 @@ pub fn setup_fullscreen_message_display(
                         TextStyle {
                             font: fonts.mono.clone(),
                             font_size: 4.0 * PIXEL_SCALE as f32,
-                            color: COLORS[15].into(),
+                            color: Color::from(COLORS[15]).with_a(0.0),
                         },
                     ),
                     style: Style {
@@ -91,6 +243,208 @@ pub fn setup_fullscreen_message_display(
         });
 }
 
+pub fn setup_retry_button(commands: &mut Commands, window: &Window, fonts: &Fonts) {
+    let top = window.height() / 2.0 + (4 * PIXEL_SCALE) as f32;
+    let center_x = window.width() / 2.0;
+
+    commands
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(top),
+                    left: Val::Px(center_x - (8 * PIXEL_SCALE) as f32),
+                    width: Val::Px((16 * PIXEL_SCALE) as f32),
+                    padding: UiRect::all(Val::Px(PIXEL_SCALE as f32)),
+                    justify_content: JustifyContent::Center,
+                    ..Default::default()
+                },
+                background_color: COLORS[8].into(),
+                ..Default::default()
+            },
+            RetryButton,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "RETRY",
+                TextStyle {
+                    font: fonts.mono.clone(),
+                    font_size: 2.0 * PIXEL_SCALE as f32,
+                    color: COLORS[15].into(),
+                },
+            ));
+        });
+
+    let hint = "Press R to retry";
+    commands.spawn(TextBundle {
+        text: Text::from_section(
+            hint,
+            TextStyle {
+                font: fonts.mono.clone(),
+                font_size: 1.5 * PIXEL_SCALE as f32,
+                color: COLORS[7].into(),
+            },
+        ),
+        style: Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(top + (6 * PIXEL_SCALE) as f32),
+            left: Val::Px(center_x - (hint.len() as f32 * 0.75 * PIXEL_SCALE as f32) / 2.0),
+            ..Default::default()
+        },
+        ..Default::default()
+    });
+}
+
+pub fn setup_lobby_portraits(
+    commands: &mut Commands,
+    window: &Window,
+    fonts: &Fonts,
+    number_of_players: u8,
+) {
+    let portrait_distance = 2 * PIXEL_SCALE;
+    let slot_width = TILE_WIDTH + 2 * PIXEL_SCALE /* border */;
+    let total_width =
+        number_of_players as u32 * slot_width + (number_of_players - 1) as u32 * portrait_distance;
+
+    let top = window.height() / 2.0 + (4 * PIXEL_SCALE) as f32;
+    let offset_x = window.width() / 2.0 - total_width as f32 / 2.0;
+
+    for i in 0..number_of_players as usize {
+        commands
+            .spawn((
+                NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        top: Val::Px(top),
+                        left: Val::Px(
+                            offset_x + (i as u32 * (slot_width + portrait_distance)) as f32,
+                        ),
+                        width: Val::Px(slot_width as f32),
+                        height: Val::Px((TILE_HEIGHT + 2 * PIXEL_SCALE) as f32),
+                        border: UiRect::all(Val::Px(PIXEL_SCALE as f32)),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..Default::default()
+                    },
+                    background_color: COLORS[8].into(),
+                    border_color: COLORS[0].into(),
+                    ..Default::default()
+                },
+                LobbyPortrait(i),
+            ))
+            .with_children(|parent| {
+                parent.spawn(ImageBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        width: Val::Percent(100.0),
+                        height: Val::Percent(100.0),
+                        ..Default::default()
+                    },
+                    visibility: Visibility::Hidden,
+                    ..Default::default()
+                });
+                parent.spawn(TextBundle::from_section(
+                    "?",
+                    TextStyle {
+                        font: fonts.mono.clone(),
+                        font_size: 2.0 * PIXEL_SCALE as f32,
+                        color: COLORS[15].into(),
+                    },
+                ));
+            });
+    }
+
+    commands.spawn((
+        TextBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font: fonts.mono.clone(),
+                    font_size: 2.0 * PIXEL_SCALE as f32,
+                    color: COLORS[7].into(),
+                },
+            ),
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(top + (TILE_HEIGHT + 4 * PIXEL_SCALE) as f32),
+                left: Val::Px(window.width() / 2.0 - (4 * PIXEL_SCALE) as f32),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+        LobbyConnectedCounter,
+    ));
+}
+
+pub fn setup_room_url_display(
+    commands: &mut Commands,
+    window: &Window,
+    fonts: &Fonts,
+    room_id: &str,
+) {
+    let message = format!("Room: {room_id}");
+    let top = window.height() / 2.0 + PIXEL_SCALE as f32;
+    let center_x = window.width() / 2.0;
+
+    commands.spawn((
+        TextBundle {
+            text: Text::from_section(
+                message.clone(),
+                TextStyle {
+                    font: fonts.mono.clone(),
+                    font_size: 2.0 * PIXEL_SCALE as f32,
+                    color: COLORS[7].into(),
+                },
+            ),
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(top),
+                left: Val::Px(center_x - (message.len() * PIXEL_SCALE as usize) as f32 / 2.0),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+        RoomUrlDisplay {
+            copied_message_end_frame: None,
+        },
+    ));
+}
+
+pub fn setup_freeze_message_display(
+    commands: &mut Commands,
+    window: &Window,
+    fonts: &Fonts,
+    message: &str,
+) {
+    let center_y = window.height() / 2.0 - (2 * PIXEL_SCALE) as f32;
+    let center_x = window.width() / 2.0;
+
+    commands.spawn((
+        TextBundle {
+            text: Text::from_section(
+                message,
+                TextStyle {
+                    font: fonts.mono.clone(),
+                    font_size: 4.0 * PIXEL_SCALE as f32,
+                    color: COLORS[15].into(),
+                },
+            ),
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(center_y),
+                left: Val::Px(center_x - (message.len() * PIXEL_SCALE as usize) as f32),
+                ..Default::default()
+            },
+            background_color: COLORS[0].into(),
+            // display over the HUD
+            z_index: ZIndex::Global(1),
+            ..Default::default()
+        },
+        UIComponent,
+        FreezeMessageDisplay,
+    ));
+}
+
 pub fn setup_get_ready_display(
     commands: &mut Commands,
     window: &Window,
@@ -98,6 +452,7 @@ pub fn setup_get_ready_display(
     fonts: &Fonts,
     number_of_players: u8,
     local_player_id: u8,
+    tip: &CurrentTip,
 ) {
     let portrait_distance = (12 - number_of_players) as u32 * PIXEL_SCALE;
     let total_width = number_of_players as u32 * (TILE_WIDTH + 2 * PIXEL_SCALE/* border */)
@@ -188,6 +543,29 @@ pub fn setup_get_ready_display(
                 },
                 ..Default::default()
             });
+
+            // centered tip text
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        top: Val::Px(center_y + (TILE_WIDTH / 2 + 10 * PIXEL_SCALE) as f32),
+                        width: Val::Percent(100.0),
+                        justify_content: JustifyContent::Center,
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section(
+                        tip.0.clone(),
+                        TextStyle {
+                            font: fonts.mono.clone(),
+                            font_size: 1.5 * PIXEL_SCALE as f32,
+                            color: COLORS[7].into(),
+                        },
+                    ));
+                });
         });
 }
 
@@ -207,6 +585,9 @@ fn init_hud(
     world_type: WorldType,
     game_textures: &GameTextures,
     player_ids: &[PlayerID],
+    teams: &HashMap<PlayerID, u8>,
+    round_duration_secs: u32,
+    player_hp: Option<u8>,
 ) {
     parent
         .spawn((
@@ -249,7 +630,7 @@ fn init_hud(
                         TextBundle {
                             text: Text::from_section(
                                 // TODO this is here because the ggrs systems don't seem to start immediately, so the timer has a visual issue; investigate why
-                                format_hud_time(ROUND_DURATION_SECS),
+                                format_hud_time(round_duration_secs),
                                 TextStyle {
                                     font: fonts.mono.clone(),
                                     font_size: 2.0 * PIXEL_SCALE as f32,
@@ -269,6 +650,30 @@ fn init_hud(
                     ));
                 });
 
+            // destructible wall count, right of the clock since the HUD ends right below it
+            parent.spawn((
+                TextBundle {
+                    text: Text::from_section(
+                        "",
+                        TextStyle {
+                            font: fonts.mono.clone(),
+                            font_size: 1.5 * PIXEL_SCALE as f32,
+                            color: COLORS[15].into(),
+                        },
+                    ),
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        left: Val::Px(width / 2.0 + 3.0 * PIXEL_SCALE as f32),
+                        top: Val::Px(12.25 * PIXEL_SCALE as f32),
+                        ..Default::default()
+                    },
+                    background_color: hud_colors.black_color.into(),
+                    ..Default::default()
+                },
+                UIComponent,
+                WallCountDisplay,
+            ));
+
             // network stats
             parent
                 .spawn((
@@ -333,14 +738,24 @@ fn init_hud(
                     ));
                 });
 
-            // player portraits
-            for &player_id in player_ids {
+            // player portraits, teammates are placed next to each other
+            for (slot, &player_id) in player_ids
+                .iter()
+                .sorted_by_key(|player_id| (teams.get(player_id), player_id.0))
+                .enumerate()
+            {
+                let border_color = match teams.get(&player_id) {
+                    Some(&team) => {
+                        COLORS[TEAM_COLOR_INDICES[team as usize % TEAM_COLOR_INDICES.len()]].into()
+                    }
+                    None => hud_colors.portrait_border_color,
+                };
                 parent
                     .spawn((
                         NodeBundle {
                             style: Style {
                                 position_type: PositionType::Absolute,
-                                left: Val::Px(((5 + 12 * player_id.0) as u32 * PIXEL_SCALE) as f32),
+                                left: Val::Px(((5 + 12 * slot) as u32 * PIXEL_SCALE) as f32),
                                 top: Val::Px(PIXEL_SCALE as f32),
                                 width: Val::Px(8.0 * PIXEL_SCALE as f32),
                                 height: Val::Px(10.0 * PIXEL_SCALE as f32),
@@ -352,7 +767,7 @@ fn init_hud(
                                 },
                                 ..Default::default()
                             },
-                            background_color: hud_colors.portrait_border_color.into(),
+                            background_color: border_color.into(),
                             ..Default::default()
                         },
                         PlayerPortrait(player_id),
@@ -390,16 +805,118 @@ fn init_hud(
                                 ));
                             });
                     });
+
+                if player_hp.is_some() {
+                    parent
+                        .spawn((
+                            NodeBundle {
+                                style: Style {
+                                    position_type: PositionType::Absolute,
+                                    left: Val::Px(((5 + 12 * slot) as u32 * PIXEL_SCALE) as f32),
+                                    top: Val::Px(11.5 * PIXEL_SCALE as f32),
+                                    width: Val::Px(8.0 * PIXEL_SCALE as f32),
+                                    height: Val::Px(PIXEL_SCALE as f32),
+                                    ..Default::default()
+                                },
+                                background_color: hud_colors.black_color.into(),
+                                ..Default::default()
+                            },
+                            UIComponent,
+                        ))
+                        .with_children(|parent| {
+                            parent.spawn((
+                                NodeBundle {
+                                    style: Style {
+                                        width: Val::Percent(100.0),
+                                        height: Val::Percent(100.0),
+                                        ..Default::default()
+                                    },
+                                    background_color: COLORS[10].into(),
+                                    ..Default::default()
+                                },
+                                PlayerHealthBar(player_id),
+                                UIComponent,
+                            ));
+                        });
+                }
             }
         });
 }
 
+// recursive backtracker over the cells on odd coordinates, returns the walls left between them
+pub fn generate_maze(rng: &mut SessionRng, map_size: MapSize) -> HashSet<Position> {
+    let mut wall_positions: HashSet<Position> = (1..map_size.rows - 1)
+        .flat_map(|y| (1..map_size.columns - 1).map(move |x| Position { y, x }))
+        .filter(|p| (p.y % 2 == 1) != (p.x % 2 == 1))
+        .collect();
+
+    let get_neighbouring_cell = |cell: Position, direction: Direction| -> Option<Position> {
+        let in_bounds = match direction {
+            Direction::Left => cell.x >= 3,
+            Direction::Right => cell.x + 2 <= map_size.columns - 2,
+            Direction::Up => cell.y >= 3,
+            Direction::Down => cell.y + 2 <= map_size.rows - 2,
+        };
+        if in_bounds {
+            cell.checked_offset(direction, 2)
+        } else {
+            None
+        }
+    };
+
+    let start_cell = Position { y: 1, x: 1 };
+    let mut visited_cells = HashSet::new();
+    visited_cells.insert(start_cell);
+    let mut stack = vec![start_cell];
+    while let Some(&cell) = stack.last() {
+        let mut directions = Direction::LIST;
+        shuffle(&mut directions, rng);
+
+        if let Some((direction, next_cell)) = directions.into_iter().find_map(|direction| {
+            get_neighbouring_cell(cell, direction)
+                .filter(|next_cell| !visited_cells.contains(next_cell))
+                .map(|next_cell| (direction, next_cell))
+        }) {
+            // carve a passage
+            // the neighbouring cell is in bounds, so the passage between them is as well
+            wall_positions.remove(&cell.checked_offset(direction, 1).unwrap());
+            visited_cells.insert(next_cell);
+            stack.push(next_cell);
+        } else {
+            stack.pop();
+        }
+    }
+
+    wall_positions
+}
+
+fn get_reachable_positions(
+    start_position: Position,
+    impassable_positions: &HashSet<Position>,
+) -> HashSet<Position> {
+    let mut reachable_positions = HashSet::new();
+    reachable_positions.insert(start_position);
+    let mut queue = VecDeque::from([start_position]);
+    while let Some(position) = queue.pop_front() {
+        for next_position in position.neighbors().into_iter().flatten() {
+            if !impassable_positions.contains(&next_position)
+                && reachable_positions.insert(next_position)
+            {
+                queue.push_back(next_position);
+            }
+        }
+    }
+
+    reachable_positions
+}
+
 fn spawn_map(
     rng: &mut SessionRng,
     commands: &mut Commands,
     game_textures: &GameTextures,
     world_type: WorldType,
     map_size: MapSize,
+    game_config: &GameConfig,
     player_spawn_positions: &[Position],
 ) {
     // place empty/passable tiles
@@ -461,48 +978,183 @@ fn spawn_map(
         ));
     }
 
-    let mut destructible_wall_potential_positions: HashSet<Position> = (0..map_size.rows)
-        .flat_map(|y| (0..map_size.columns).map(move |x| Position { y, x }))
-        .filter(|p| !stone_wall_positions.contains(p))
-        .collect();
+    let destructible_wall_positions = match game_config.map_layout {
+        MapLayout::Random => {
+            let mut destructible_wall_potential_positions: HashSet<Position> = (0..map_size.rows)
+                .flat_map(|y| (0..map_size.columns).map(move |x| Position { y, x }))
+                .filter(|p| !stone_wall_positions.contains(p))
+                .collect();
 
-    let number_of_passable_positions = destructible_wall_potential_positions.len();
+            let number_of_passable_positions = destructible_wall_potential_positions.len();
 
-    // reserve room for the players (cross-shaped)
-    for player_spawn_position in player_spawn_positions {
-        destructible_wall_potential_positions.remove(player_spawn_position);
-        for position in Direction::LIST
-            .iter()
-            .map(|direction| player_spawn_position.offset(*direction, 1))
-        {
-            destructible_wall_potential_positions.remove(&position);
+            // reserve room for the players (cross-shaped)
+            for player_spawn_position in player_spawn_positions {
+                destructible_wall_potential_positions.remove(player_spawn_position);
+                for position in player_spawn_position.neighbors().into_iter().flatten() {
+                    destructible_wall_potential_positions.remove(&position);
+                }
+            }
+
+            let mut num_of_destructible_walls_to_place =
+                number_of_passable_positions * game_config.wall_density_permille as usize / 1000;
+            if destructible_wall_potential_positions.len() < num_of_destructible_walls_to_place {
+                warn!(
+                    "Not enough passable positions available for placing destructible walls. Have {}, but need {}. Placing only {}.",
+                    destructible_wall_potential_positions.len(),
+                    num_of_destructible_walls_to_place,
+                    destructible_wall_potential_positions.len()
+                );
+                num_of_destructible_walls_to_place = destructible_wall_potential_positions.len();
+            }
+
+            match game_config.map_symmetry {
+                MapSymmetry::None => {
+                    let mut destructible_wall_positions = destructible_wall_potential_positions
+                        .into_iter()
+                        .sorted()
+                        .collect_vec();
+                    shuffle(&mut destructible_wall_positions, rng);
+                    destructible_wall_positions.truncate(num_of_destructible_walls_to_place);
+                    destructible_wall_positions
+                }
+                MapSymmetry::Rotational180 => {
+                    let mirror = |position: Position| Position {
+                        y: map_size.rows - 1 - position.y,
+                        x: map_size.columns - 1 - position.x,
+                    };
+
+                    // pick from one half of the map, both the position and its mirror must be free
+                    let mut half_positions = destructible_wall_potential_positions
+                        .iter()
+                        .copied()
+                        .filter(|&position| {
+                            position <= mirror(position)
+                                && destructible_wall_potential_positions.contains(&mirror(position))
+                        })
+                        .sorted()
+                        .collect_vec();
+                    shuffle(&mut half_positions, rng);
+
+                    let mut destructible_wall_positions = vec![];
+                    for position in half_positions {
+                        if destructible_wall_positions.len() >= num_of_destructible_walls_to_place {
+                            break;
+                        }
+
+                        destructible_wall_positions.push(position);
+                        // the center of the map is its own mirror
+                        if mirror(position) != position {
+                            destructible_wall_positions.push(mirror(position));
+                        }
+                    }
+                    destructible_wall_positions
+                }
+            }
         }
-    }
+        MapLayout::Maze => {
+            let mut maze_wall_positions = generate_maze(rng, map_size);
+
+            // reserve room for the players (cross-shaped)
+            for player_spawn_position in player_spawn_positions {
+                maze_wall_positions.remove(player_spawn_position);
+                for position in player_spawn_position.neighbors().into_iter().flatten() {
+                    maze_wall_positions.remove(&position);
+                }
+            }
 
-    let number_of_players = player_spawn_positions.len();
-    let num_of_destructible_walls_to_place = match number_of_players {
-        2..=3 => number_of_passable_positions / 5 * 2,
-        4..=8 => number_of_passable_positions / 2,
-        _ => unreachable!(),
+            // every passable position should be reachable from every spawn without blasting through walls
+            let impassable_positions: HashSet<Position> = stone_wall_positions
+                .union(&maze_wall_positions)
+                .copied()
+                .collect();
+            let reachable_positions =
+                get_reachable_positions(player_spawn_positions[0], &impassable_positions);
+            assert!(player_spawn_positions
+                .iter()
+                .all(|p| reachable_positions.contains(p)));
+            assert!((0..map_size.rows)
+                .flat_map(|y| (0..map_size.columns).map(move |x| Position { y, x }))
+                .filter(|p| !impassable_positions.contains(p))
+                .all(|p| reachable_positions.contains(&p)));
+
+            maze_wall_positions.into_iter().sorted().collect_vec()
+        }
     };
-    if destructible_wall_potential_positions.len() < num_of_destructible_walls_to_place {
-        panic!(
-            "Not enough passable positions available for placing destructible walls. Have {}, but need at least {}",
-            destructible_wall_potential_positions.len(),
-            num_of_destructible_walls_to_place
-        );
-    }
 
-    let mut destructible_wall_positions = destructible_wall_potential_positions
-        .into_iter()
-        .sorted()
-        .collect_vec();
-    shuffle(&mut destructible_wall_positions, rng);
-    for position in destructible_wall_positions
+    assert!(destructible_wall_positions
         .iter()
-        .take(num_of_destructible_walls_to_place)
-        .cloned()
-    {
+        .chain(player_spawn_positions)
+        .all(|p| p.in_bounds(map_size)));
+
+    // meltwater puddles and thin ice
+    if world_type == WorldType::IceWorld {
+        let occupied_positions: HashSet<Position> = stone_wall_positions
+            .iter()
+            .chain(&destructible_wall_positions)
+            .chain(player_spawn_positions)
+            .copied()
+            .collect();
+        let mut free_positions = (1..map_size.rows - 1)
+            .flat_map(|y| (1..map_size.columns - 1).map(move |x| Position { y, x }))
+            .filter(|p| !occupied_positions.contains(p))
+            .collect_vec();
+        let number_of_water_tiles = (free_positions.len() as f32 * WATER_TILE_DENSITY) as usize;
+        let number_of_crackable_ice_tiles =
+            (free_positions.len() as f32 * CRACKABLE_ICE_DENSITY) as usize;
+        shuffle(&mut free_positions, rng);
+        let mut free_positions = free_positions.into_iter();
+        let water_tile_positions = free_positions
+            .by_ref()
+            .take(number_of_water_tiles)
+            .collect_vec();
+        let crackable_ice_positions = free_positions.take(number_of_crackable_ice_tiles);
+
+        for position in water_tile_positions {
+            commands.spawn((
+                SpriteBundle {
+                    transform: Transform::from_xyz(
+                        get_x(position.x),
+                        get_y(position.y),
+                        WATER_Z_LAYER,
+                    ),
+                    sprite: Sprite {
+                        color: Color::from(COLORS[1]).with_a(0.6),
+                        custom_size: Some(Vec2::new(TILE_WIDTH as f32, TILE_HEIGHT as f32)),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                WaterTile,
+                position,
+            ));
+        }
+
+        for position in crackable_ice_positions {
+            commands
+                .spawn((
+                    SpriteBundle {
+                        texture: game_textures.get_map_textures(world_type).empty.clone(),
+                        transform: Transform::from_xyz(
+                            get_x(position.x),
+                            get_y(position.y),
+                            CRACKABLE_ICE_Z_LAYER,
+                        ),
+                        sprite: Sprite {
+                            custom_size: Some(Vec2::new(TILE_WIDTH as f32, TILE_HEIGHT as f32)),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    },
+                    CrackableIce {
+                        hp: CRACKABLE_ICE_HP,
+                    },
+                    position,
+                ))
+                .add_rollback();
+        }
+    }
+
+    for position in destructible_wall_positions {
         commands
             .spawn((
                 SpriteBundle {
@@ -530,6 +1182,47 @@ fn spawn_map(
     }
 }
 
+pub fn player_spawn_positions(map_size: MapSize) -> Vec<Position> {
+    let (rows, columns) = (map_size.rows, map_size.columns);
+    // odd column so the position never lands on a stone wall
+    let side_x = (columns / 4) | 1;
+    let positions = vec![
+        Position { y: 1, x: 1 },
+        Position {
+            y: rows - 2,
+            x: columns - 2,
+        },
+        Position {
+            y: 1,
+            x: columns - 2,
+        },
+        Position { y: rows - 2, x: 1 },
+        Position {
+            y: rows / 2,
+            x: side_x,
+        },
+        Position {
+            y: rows / 2,
+            x: columns - 1 - side_x,
+        },
+        Position {
+            y: 1,
+            x: columns / 2,
+        },
+        Position {
+            y: rows - 2,
+            x: columns / 2,
+        },
+    ];
+
+    debug_assert!(positions
+        .iter()
+        .tuple_combinations()
+        .all(|(a, b)| a.manhattan_distance(b) > 3));
+
+    positions
+}
+
 pub fn setup_round(
     rng: &mut SessionRng,
     commands: &mut Commands,
@@ -538,9 +1231,13 @@ pub fn setup_round(
     game_textures: &GameTextures,
     fonts: &Fonts,
     hud_colors: &HUDColors,
+    game_config: &GameConfig,
     number_of_players: u8,
+    teams: &HashMap<PlayerID, u8>,
     round_start_frame: u32,
 ) {
+    map_size.validate().expect("invalid map size");
+
     let player_ids = (0..number_of_players)
         .map(PlayerID)
         .collect::<Vec<PlayerID>>();
@@ -569,54 +1266,105 @@ pub fn setup_round(
                 world_type,
                 game_textures,
                 &player_ids,
+                teams,
+                game_config.round_duration_secs,
+                game_config.player_hp,
             );
         });
 
     // Map generation //
-    let possible_player_spawn_positions = [
-        (1, 1),
-        (map_size.rows - 2, map_size.columns - 2),
-        (1, map_size.columns - 2),
-        (map_size.rows - 2, 1),
-        (3, 5),
-        (map_size.rows - 4, map_size.columns - 6),
-        (3, map_size.columns - 6),
-        (map_size.rows - 4, 5),
-    ];
-    let mut possible_player_spawn_positions = possible_player_spawn_positions
-        .iter()
-        .map(|(y, x)| Position { y: *y, x: *x });
+    let mut possible_player_spawn_positions = player_spawn_positions(map_size);
+    // only shuffle the positions in use so the players stay spread out
+    shuffle(
+        &mut possible_player_spawn_positions[..number_of_players as usize],
+        rng,
+    );
+    let mut possible_player_spawn_positions = possible_player_spawn_positions.into_iter();
 
     let mut player_spawn_positions = vec![];
     for player_id in player_ids {
         let player_spawn_position = possible_player_spawn_positions.next().unwrap();
         let base_texture = game_textures.get_player_texture(player_id).clone();
-        commands
-            .spawn((
-                SpriteBundle {
-                    texture: base_texture.clone(),
-                    transform: Transform::from_xyz(
-                        get_x(player_spawn_position.x),
-                        get_y(player_spawn_position.y),
-                        PLAYER_Z_LAYER,
-                    ),
-                    sprite: Sprite {
-                        custom_size: Some(Vec2::new(TILE_WIDTH as f32, TILE_HEIGHT as f32)),
-                        ..Default::default()
-                    },
+        let mut player_entity_commands = commands.spawn((
+            SpriteBundle {
+                texture: base_texture.clone(),
+                transform: Transform::from_xyz(
+                    get_x(player_spawn_position.x),
+                    get_y(player_spawn_position.y),
+                    PLAYER_Z_LAYER,
+                ),
+                sprite: Sprite {
+                    custom_size: Some(Vec2::new(TILE_WIDTH as f32, TILE_HEIGHT as f32)),
                     ..Default::default()
                 },
-                Player {
-                    id: player_id,
-                    can_push_bombs: false,
-                },
-                player_spawn_position,
-                BombSatchel {
-                    bombs_available: 1,
-                    bomb_range: 2,
-                },
-            ))
-            .add_rollback();
+                ..Default::default()
+            },
+            Player {
+                id: player_id,
+                can_push_bombs: false,
+                magnet_radius: 0,
+                last_move_direction: None,
+                move_cooldown_frames: PLAYER_MOVE_FRAME_INTERVAL,
+                next_move_frame: 0,
+                pending_move: None,
+                stunned_until_frame: 0,
+                team: teams.get(&player_id).copied(),
+            },
+            player_spawn_position,
+            BombSatchel {
+                bombs_available: game_config.initial_bombs,
+                bomb_range: game_config.initial_range,
+                has_remote_detonator: false,
+                has_pierce: false,
+            },
+        ));
+        player_entity_commands.add_rollback();
+        player_entity_commands.with_children(|parent| {
+            let bomb_count_text = |color: Color| {
+                Text::from_section(
+                    game_config.initial_bombs.to_string(),
+                    TextStyle {
+                        font: fonts.mono.clone(),
+                        font_size: 1.5 * PIXEL_SCALE as f32,
+                        color,
+                    },
+                )
+                .with_alignment(TextAlignment::Center)
+            };
+            let overlay_y = (TILE_HEIGHT / 2 + PIXEL_SCALE) as f32;
+
+            // shadow
+            parent
+                .spawn((
+                    Text2dBundle {
+                        text: bomb_count_text(COLORS[0].into()),
+                        transform: Transform::from_xyz(
+                            PIXEL_SCALE as f32 / 4.0,
+                            overlay_y - PIXEL_SCALE as f32 / 4.0,
+                            0.5,
+                        ),
+                        ..Default::default()
+                    },
+                    BombCountOverlay,
+                ))
+                .add_rollback();
+            parent
+                .spawn((
+                    Text2dBundle {
+                        text: bomb_count_text(COLORS[15].into()),
+                        transform: Transform::from_xyz(0.0, overlay_y, 1.0),
+                        ..Default::default()
+                    },
+                    BombCountOverlay,
+                ))
+                .add_rollback();
+        });
+        if let Some(hp) = game_config.player_hp {
+            player_entity_commands.insert(PlayerHP {
+                current: hp,
+                max: hp,
+            });
+        }
 
         player_spawn_positions.push(player_spawn_position);
     }
@@ -627,12 +1375,16 @@ pub fn setup_round(
         game_textures,
         world_type,
         map_size,
+        game_config,
         &player_spawn_positions,
     );
 
-    commands.insert_resource(GameEndFrame(round_start_frame + ROUND_DURATION_SECS * FPS));
+    let round_duration_frames = game_config.round_duration_secs * FPS;
+    commands.insert_resource(GameEndFrame(round_start_frame + round_duration_frames));
+    commands.remove_resource::<OvertimeActive>();
     commands.insert_resource(WallOfDeath::Dormant {
-        activation_frame: round_start_frame + ROUND_DURATION_SECS / 2 * FPS,
+        activation_frame: round_start_frame
+            + round_duration_frames * game_config.wall_of_death_activation_permille / 1000,
     });
 }
 
@@ -640,18 +1392,34 @@ pub fn generate_item_at_position(
     rng: &mut SessionRng,
     commands: &mut Commands,
     game_textures: &GameTextures,
+    loot_table: &LootTable,
     position: Position,
 ) {
-    let roll = rng.gen_u64() % 100;
-
-    /* "Loot tables" */
-    let item = match roll {
-        _ if roll < 50 => Item::BombsUp,
-        50..=89 => Item::RangeUp,
-        _ if roll >= 90 => Item::BombPush,
-        _ => unreachable!(),
-    };
+    let total_weight: u64 = loot_table.entries.iter().map(|e| e.1).sum();
+    let mut roll = rng.gen_range(0, total_weight);
+
+    let item = loot_table
+        .entries
+        .iter()
+        .find_map(|&(item, weight)| {
+            if roll < weight {
+                Some(item)
+            } else {
+                roll -= weight;
+                None
+            }
+        })
+        .unwrap();
+
+    spawn_item(commands, game_textures, position, item);
+}
 
+pub fn spawn_item(
+    commands: &mut Commands,
+    game_textures: &GameTextures,
+    position: Position,
+    item: Item,
+) {
     commands
         .spawn((
             SpriteBundle {
@@ -659,6 +1427,13 @@ pub fn generate_item_at_position(
                     Item::BombsUp => game_textures.bombs_up.clone(),
                     Item::RangeUp => game_textures.range_up.clone(),
                     Item::BombPush => game_textures.bomb_push.clone(),
+                    Item::SpeedUp => game_textures.speed_up.clone(),
+                    Item::BombDown => game_textures.bomb_down.clone(),
+                    Item::RangeDown => game_textures.range_down.clone(),
+                    Item::Skull => game_textures.skull.clone(),
+                    Item::RemoteBomb => game_textures.remote_bomb.clone(),
+                    Item::Pierce => game_textures.pierce.clone(),
+                    Item::Magnet => game_textures.magnet.clone(),
                 },
                 transform: Transform::from_xyz(get_x(position.x), get_y(position.y), ITEM_Z_LAYER),
                 sprite: Sprite {
@@ -679,12 +1454,15 @@ pub fn burn_item(
     item_entity: Entity,
     item_texture: &mut Handle<Image>,
     current_frame: u32,
+    burn_duration_frames: u32,
+    hop: u8,
 ) {
     commands
         .entity(item_entity)
         .remove::<Item>()
         .insert(BurningItem {
-            expiration_frame: current_frame + FPS / 2,
+            expiration_frame: current_frame + burn_duration_frames + hop as u32 * FPS / 4,
+            hop,
         });
     *item_texture = game_textures.burning_item.clone();
 }
@@ -698,7 +1476,31 @@ pub fn setup_leaderboard_display(
     fonts: &Fonts,
     leaderboard: &Leaderboard,
     round_outcome: RoundOutcome,
+    game_stats: &GameStats,
 ) {
+    let stat =
+        |stats: &HashMap<PlayerID, u32>, player_id| stats.get(&player_id).copied().unwrap_or(0);
+
+    // ties go to the lower player ID
+    let longest_chain = game_stats
+        .longest_chain
+        .iter()
+        .map(|(&player_id, &chain_length)| (player_id, chain_length))
+        .max_by_key(|(player_id, chain_length)| (*chain_length, u8::MAX - player_id.0));
+
+    // rank by score, ties are broken by the walked distance
+    let mut ranked_scores = leaderboard
+        .tournament_scores()
+        .into_iter()
+        .collect::<Vec<_>>();
+    ranked_scores.sort_by_key(|(player_id, score)| {
+        (
+            std::cmp::Reverse(*score),
+            std::cmp::Reverse(stat(&game_stats.tiles_walked, *player_id)),
+            player_id.0,
+        )
+    });
+
     parent
         .spawn((
             NodeBundle {
@@ -731,7 +1533,8 @@ pub fn setup_leaderboard_display(
                     LeaderboardUIContent,
                 ))
                 .with_children(|parent| {
-                    for (&player_id, &score) in &leaderboard.scores {
+                    for (row, (player_id, score)) in ranked_scores.into_iter().enumerate() {
+                        let row = row as u8;
                         // spawn player portrait
                         parent
                             .spawn((
@@ -739,9 +1542,7 @@ pub fn setup_leaderboard_display(
                                     style: Style {
                                         position_type: PositionType::Absolute,
                                         left: Val::Px(4.0 * PIXEL_SCALE as f32),
-                                        top: Val::Px(
-                                            ((6 + player_id.0 * 12) as u32 * PIXEL_SCALE) as f32,
-                                        ),
+                                        top: Val::Px(((6 + row * 12) as u32 * PIXEL_SCALE) as f32),
                                         width: Val::Px(TILE_WIDTH as f32),
                                         height: Val::Px(TILE_HEIGHT as f32),
                                         ..Default::default()
@@ -769,15 +1570,68 @@ pub fn setup_leaderboard_display(
                                 ));
                             });
 
+                        // badge for the longest bomb chain of the tournament
+                        if let Some((longest_chain_player_id, chain_length)) = longest_chain {
+                            if player_id == longest_chain_player_id {
+                                parent.spawn((
+                                    TextBundle {
+                                        text: Text::from_section(
+                                            format!("CHAIN x{chain_length}"),
+                                            TextStyle {
+                                                font: fonts.mono.clone(),
+                                                font_size: 1.5 * PIXEL_SCALE as f32,
+                                                color: COLORS[14].into(),
+                                            },
+                                        ),
+                                        style: Style {
+                                            position_type: PositionType::Absolute,
+                                            left: Val::Px(3.0 * PIXEL_SCALE as f32),
+                                            top: Val::Px(
+                                                ((14 + row * 12) as u32 * PIXEL_SCALE) as f32,
+                                            ),
+                                            ..Default::default()
+                                        },
+                                        ..Default::default()
+                                    },
+                                    UIComponent,
+                                ));
+                            }
+                        }
+
+                        // tournament stats of the player
+                        parent.spawn((
+                            TextBundle {
+                                text: Text::from_section(
+                                    format!(
+                                        "CHAIN {} ITEMS {} TILES {}",
+                                        stat(&game_stats.longest_chain, player_id),
+                                        stat(&game_stats.items_collected, player_id),
+                                        stat(&game_stats.tiles_walked, player_id),
+                                    ),
+                                    TextStyle {
+                                        font: fonts.mono.clone(),
+                                        font_size: 1.5 * PIXEL_SCALE as f32,
+                                        color: COLORS[7].into(),
+                                    },
+                                ),
+                                style: Style {
+                                    position_type: PositionType::Absolute,
+                                    left: Val::Px(15.0 * PIXEL_SCALE as f32),
+                                    top: Val::Px(((14 + row * 12) as u32 * PIXEL_SCALE) as f32),
+                                    ..Default::default()
+                                },
+                                ..Default::default()
+                            },
+                            UIComponent,
+                        ));
+
                         // spawn player trophies
                         for i in 0..score {
                             parent.spawn((
                                 ImageBundle {
                                     style: Style {
                                         position_type: PositionType::Absolute,
-                                        top: Val::Px(
-                                            ((7 + player_id.0 * 12) as u32 * PIXEL_SCALE) as f32,
-                                        ),
+                                        top: Val::Px(((7 + row * 12) as u32 * PIXEL_SCALE) as f32),
                                         left: Val::Px(((15 + i * 9) as u32 * PIXEL_SCALE) as f32),
                                         width: Val::Px(5.0 * PIXEL_SCALE as f32),
                                         height: Val::Px(7.0 * PIXEL_SCALE as f32),
@@ -790,40 +1644,65 @@ pub fn setup_leaderboard_display(
                             ));
                         }
 
-                        if let RoundOutcome::Winner(round_winner_player_id) = round_outcome {
-                            if player_id == round_winner_player_id {
-                                let mut place_text = |y, x, str: &str, c: usize| {
-                                    parent.spawn((
-                                        TextBundle {
-                                            text: Text::from_section(
-                                                str.to_string(),
-                                                TextStyle {
-                                                    font: fonts.mono.clone(),
-                                                    font_size: 2.0 * PIXEL_SCALE as f32,
-                                                    color: COLORS[c].into(),
-                                                },
-                                            ),
-                                            style: Style {
-                                                position_type: PositionType::Absolute,
-                                                top: Val::Px(y as f32 * PIXEL_SCALE as f32),
-                                                left: Val::Px(x as f32 * PIXEL_SCALE as f32),
-                                                ..Default::default()
+                        if leaderboard.score_mode == ScoreMode::Cumulative {
+                            parent.spawn((
+                                TextBundle {
+                                    text: Text::from_section(
+                                        format!("TOTAL {}", leaderboard.total_score(player_id)),
+                                        TextStyle {
+                                            font: fonts.mono.clone(),
+                                            font_size: 1.5 * PIXEL_SCALE as f32,
+                                            color: COLORS[7].into(),
+                                        },
+                                    ),
+                                    style: Style {
+                                        position_type: PositionType::Absolute,
+                                        left: Val::Px(window_width - 12.0 * PIXEL_SCALE as f32),
+                                        top: Val::Px(((14 + row * 12) as u32 * PIXEL_SCALE) as f32),
+                                        ..Default::default()
+                                    },
+                                    ..Default::default()
+                                },
+                                UIComponent,
+                            ));
+                        }
+
+                        let is_round_winner = match round_outcome {
+                            RoundOutcome::Winner(round_winner_player_id) => {
+                                player_id == round_winner_player_id
+                            }
+                            RoundOutcome::TeamWinner(team) => {
+                                leaderboard.teams.get(&player_id) == Some(&team)
+                            }
+                            RoundOutcome::Tie => false,
+                        };
+                        if is_round_winner {
+                            let mut place_text = |y, x, str: &str, c: usize| {
+                                parent.spawn((
+                                    TextBundle {
+                                        text: Text::from_section(
+                                            str.to_string(),
+                                            TextStyle {
+                                                font: fonts.mono.clone(),
+                                                font_size: 2.0 * PIXEL_SCALE as f32,
+                                                color: COLORS[c].into(),
                                             },
+                                        ),
+                                        style: Style {
+                                            position_type: PositionType::Absolute,
+                                            top: Val::Px(y as f32 * PIXEL_SCALE as f32),
+                                            left: Val::Px(x as f32 * PIXEL_SCALE as f32),
                                             ..Default::default()
                                         },
-                                        UIComponent,
-                                    ));
-                                };
-
-                                place_text(6 + player_id.0 * 12, 15 + (score - 1) * 9 - 2, "*", 15);
-                                place_text(8 + player_id.0 * 12, 15 + (score - 1) * 9 + 6, "*", 15);
-                                place_text(
-                                    10 + player_id.0 * 12,
-                                    15 + (score - 1) * 9 - 1,
-                                    "*",
-                                    15,
-                                );
-                            }
+                                        ..Default::default()
+                                    },
+                                    UIComponent,
+                                ));
+                            };
+
+                            place_text(6 + row * 12, 15 + (score - 1) * 9 - 2, "*", 15);
+                            place_text(8 + row * 12, 15 + (score - 1) * 9 + 6, "*", 15);
+                            place_text(10 + row * 12, 15 + (score - 1) * 9 - 1, "*", 15);
                         }
                     }
                 });
@@ -842,7 +1721,7 @@ pub fn setup_leaderboard_display(
                         },
                         background_color: (*COLORS
                             .iter()
-                            .nth(rng.gen_u64() as usize % COLORS.len())
+                            .nth(rng.gen_range(0, COLORS.len() as u64) as usize)
                             .unwrap())
                         .into(),
                         ..Default::default()
@@ -875,6 +1754,7 @@ pub fn setup_tournament_winner_display(
     game_textures: &GameTextures,
     fonts: &Fonts,
     winner: PlayerID,
+    session_secs: u32,
 ) {
     let center_y = window_height / 2.0 - (4 * PIXEL_SCALE) as f32 /* accounting for the chicken dinner text */;
     let center_x = window_width / 2.0;
@@ -963,4 +1843,66 @@ pub fn setup_tournament_winner_display(
         "WINNER WINNER CHICKEN DINNER!",
         15,
     );
+    place_text(
+        (TILE_WIDTH / PIXEL_SCALE / 2) as isize + 7,
+        -10,
+        &format!("Session time: {}", format_hud_time(session_secs)),
+        7,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tint_toward() {
+        let tinted = tint_toward(Color::BLACK, Color::WHITE, 0.3);
+        assert!((tinted.r() - 0.3).abs() < f32::EPSILON);
+        assert!((tinted.g() - 0.3).abs() < f32::EPSILON);
+        assert!((tinted.b() - 0.3).abs() < f32::EPSILON);
+
+        let base = Color::rgba(0.2, 0.4, 0.6, 0.5);
+        let tint = Color::rgb(1.0, 0.0, 0.5);
+        assert_eq!(tint_toward(base, tint, 0.0), base);
+        assert_eq!(tint_toward(base, tint, 1.0), tint.with_a(0.5));
+    }
+
+    #[test]
+    fn test_bomb_tint() {
+        assert_eq!(bomb_tint(None), Color::default());
+        // the first player's color is white, which leaves the bomb untinted
+        assert_eq!(bomb_tint(Some(PlayerID(0))), Color::default());
+        assert_ne!(bomb_tint(Some(PlayerID(1))), Color::default());
+    }
+
+    fn assert_spawn_positions_separated(map_size: MapSize) {
+        let positions = player_spawn_positions(map_size);
+        assert_eq!(positions.len(), 8);
+        for (a, b) in positions.iter().tuple_combinations() {
+            assert!(
+                a.manhattan_distance(b) > 3,
+                "{:?} and {:?} are too close",
+                a,
+                b
+            );
+        }
+        assert!(positions.iter().all(|p| p.in_bounds(map_size)));
+    }
+
+    #[test]
+    fn test_player_spawn_positions_small_map() {
+        assert_spawn_positions_separated(MapSize {
+            rows: 9,
+            columns: 13,
+        });
+    }
+
+    #[test]
+    fn test_player_spawn_positions_large_map() {
+        assert_spawn_positions_separated(MapSize {
+            rows: 15,
+            columns: 19,
+        });
+    }
 }
@@ -2,3 +2,27 @@ export function doneLoading() {
     document.getElementById('button-loading').remove();
     document.getElementById('button-start').removeAttribute("hidden");
 }
+
+let roomUrlCopied = false;
+
+// the clipboard is not available if the page is not served over HTTPS
+export function copyRoomUrl(roomId) {
+    if (!navigator.clipboard) {
+        console.warn("The clipboard is not available, the room URL was not copied");
+        return;
+    }
+
+    var url = new URL(window.location.href);
+    url.search = "";
+    url.searchParams.set("room", roomId);
+    navigator.clipboard.writeText(url.toString())
+        .then(() => { roomUrlCopied = true; })
+        .catch((err) => console.warn("Failed to copy the room URL:", err));
+}
+
+// returns true once after the room URL was written to the clipboard
+export function takeRoomUrlCopied() {
+    const copied = roomUrlCopied;
+    roomUrlCopied = false;
+    return copied;
+}
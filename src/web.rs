@@ -7,7 +7,11 @@ use parking_lot::RwLock;
 use wasm_bindgen::prelude::wasm_bindgen;
 
 use crate::{
-    constants::{INPUT_ACTION, INPUT_DOWN, INPUT_LEFT, INPUT_RIGHT, INPUT_UP},
+    components::RoomUrlDisplay,
+    constants::{
+        FPS, INPUT_ACTION, INPUT_ACTION_HELD, INPUT_DETONATE, INPUT_DOWN, INPUT_EMOTE_1,
+        INPUT_EMOTE_2, INPUT_LEFT, INPUT_RIGHT, INPUT_UP,
+    },
     resources::{GameFreeze, MatchboxConfig},
     types::{GgrsConfig, ICEServerConfig, PlayerInput},
     AppState,
@@ -57,12 +61,85 @@ pub fn set_input_active(input: u8) {
 #[wasm_bindgen(module = "/src/wasm_callables.js")]
 extern "C" {
     pub fn doneLoading();
+    pub fn copyRoomUrl(room_id: &str);
+    pub fn takeRoomUrlCopied() -> bool;
 }
 
 // web-specific systems
 pub fn web_ready_to_start_enter() {
     // TODO: would it be better to do this through web-sys?
     doneLoading();
+
+    #[cfg(feature = "touch-controls")]
+    if let Err(err) = spawn_touch_controls() {
+        warn!("Failed to spawn the touch controls: {err:?}");
+    }
+}
+
+#[cfg(feature = "touch-controls")]
+fn spawn_touch_controls() -> Result<(), wasm_bindgen::JsValue> {
+    use wasm_bindgen::{closure::Closure, JsCast};
+
+    const TOUCH_CONTROLS_STYLE: &str = "
+        :root {
+            --touch-control-opacity: 0.5;
+            --touch-control-size: 64px;
+        }
+        .touch-control {
+            position: absolute;
+            width: var(--touch-control-size);
+            height: var(--touch-control-size);
+            opacity: var(--touch-control-opacity);
+            touch-action: none;
+        }
+        #touch-control-up { left: var(--touch-control-size); bottom: calc(2 * var(--touch-control-size)); }
+        #touch-control-down { left: var(--touch-control-size); bottom: 0; }
+        #touch-control-left { left: 0; bottom: var(--touch-control-size); }
+        #touch-control-right { left: calc(2 * var(--touch-control-size)); bottom: var(--touch-control-size); }
+        #touch-control-action { right: var(--touch-control-size); bottom: var(--touch-control-size); }
+    ";
+
+    let document = web_sys::window()
+        .and_then(|window| window.document())
+        .ok_or("no document")?;
+
+    let style = document.create_element("style")?;
+    style.set_text_content(Some(TOUCH_CONTROLS_STYLE));
+    document.head().ok_or("no head")?.append_child(&style)?;
+
+    let body = document.body().ok_or("no body")?;
+    for (id, label, input_action) in [
+        ("touch-control-up", "▲", InputAction::Up),
+        ("touch-control-down", "▼", InputAction::Down),
+        ("touch-control-left", "◄", InputAction::Left),
+        ("touch-control-right", "►", InputAction::Right),
+        ("touch-control-action", "●", InputAction::Space),
+    ] {
+        let button = document.create_element("button")?;
+        button.set_id(id);
+        button.set_class_name("touch-control");
+        button.set_text_content(Some(label));
+
+        // passive listeners don't block scrolling, so `touch-action: none` is relied on for that instead
+        let mut options = web_sys::AddEventListenerOptions::new();
+        options.passive(true);
+
+        let on_touch_start = Closure::<dyn FnMut(web_sys::TouchEvent)>::new(move |_| {
+            set_input_active(input_action as u8);
+        });
+        button.add_event_listener_with_callback_and_add_event_listener_options(
+            "touchstart",
+            on_touch_start.as_ref().unchecked_ref(),
+            &options,
+        )?;
+        // the buttons live until the page is closed
+        // NOTE: inputs are registered on press only, so there is no need to handle `touchend`
+        on_touch_start.forget();
+
+        body.append_child(&button)?;
+    }
+
+    Ok(())
 }
 
 pub fn web_ready_to_start_update(
@@ -110,25 +187,57 @@ pub fn web_ready_to_start_update(
             room_id,
             matchbox_server_url,
             ice_server_config,
+            desync_detection_interval: Some(1),
+            player_input_delays: vec![],
+            #[cfg(debug_assertions)]
+            simulated_packet_loss: 0,
+            preferred_world_type: None,
+            voted_world_type: None,
+            team_assignments: None,
         });
         next_state.set(AppState::Lobby);
     }
 }
 
+// discriminants match the values passed to `set_input_active`
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub enum InputAction {
-    Up,
-    Down,
-    Left,
-    Right,
-    Space,
+    Up = 0,
+    Down = 1,
+    Left = 2,
+    Right = 3,
+    Space = 4,
+}
+
+pub fn copy_room_url(
+    matchbox_config: Res<MatchboxConfig>,
+    rendered_frame_count: Res<bevy::core::FrameCount>,
+    mut room_url_display_query: Query<(&mut Text, &mut RoomUrlDisplay)>,
+) {
+    // quick join rooms are matched automatically, there is nothing to share
+    if matchbox_config.room_id == "quick_join" {
+        return;
+    }
+
+    // the clipboard is written asynchronously, the confirmation is only shown once that succeeded
+    let copied = takeRoomUrlCopied();
+    for (mut text, mut room_url_display) in room_url_display_query.iter_mut() {
+        if room_url_display.is_added() {
+            copyRoomUrl(&matchbox_config.room_id);
+        }
+
+        if copied {
+            text.sections[0].value = "URL copied!".to_string();
+            room_url_display.copied_message_end_frame = Some(rendered_frame_count.0 + FPS);
+        }
+    }
 }
 
 pub fn web_input(
     mut commands: Commands,
     keyboard_input: Res<Input<KeyCode>>,
     local_players: Res<LocalPlayers>,
-    mut last_kb_input: Local<u8>,
+    mut last_kb_input: Local<u16>,
     game_freeze: Option<Res<GameFreeze>>,
 ) {
     // there must be only one local player
@@ -136,7 +245,7 @@ pub fn web_input(
     let local_player_handle = *local_players.0.first().unwrap();
 
     // process web UI input
-    let mut web_input: u8 = 0;
+    let mut web_input: u16 = 0;
 
     let mut inputs = INPUTS.write();
     while let Some(input) = inputs.pop_back() {
@@ -169,7 +278,7 @@ pub fn web_input(
     }
 
     // process keyboard input
-    let mut kb_input: u8 = 0;
+    let mut kb_input: u16 = 0;
 
     if keyboard_input.pressed(KeyCode::Up) {
         kb_input |= INPUT_UP;
@@ -186,11 +295,25 @@ pub fn web_input(
     if keyboard_input.pressed(KeyCode::Space) {
         kb_input |= INPUT_ACTION;
     }
+    if keyboard_input.pressed(KeyCode::R) {
+        kb_input |= INPUT_DETONATE;
+    }
+    if keyboard_input.pressed(KeyCode::Q) {
+        kb_input |= INPUT_EMOTE_1;
+    }
+    if keyboard_input.pressed(KeyCode::E) {
+        kb_input |= INPUT_EMOTE_2;
+    }
 
     // merge the inputs while only acknowledging new keyboard input
-    let input = !*last_kb_input & kb_input | web_input;
+    let mut input = !*last_kb_input & kb_input | web_input;
     *last_kb_input = kb_input;
 
+    // the held state is sent on every frame so that hold-to-bomb can tell a hold from a tap
+    if kb_input & INPUT_ACTION != 0 {
+        input |= INPUT_ACTION_HELD;
+    }
+
     let mut local_inputs = HashMap::new();
     if game_freeze.is_some() {
         // override inputs during a freeze as the game must not be rolled back at this time
@@ -112,6 +112,11 @@ document.addEventListener('dblclick', function (event) {
 window.onload = () => {
     // prevent non-number room ID input
     var roomIdInput = document.getElementById('roomID');
+    // prefill the room ID from shared room URLs
+    var sharedRoomId = new URLSearchParams(window.location.search).get("room");
+    if (sharedRoomId !== null && /^([0-9]{4})$/.test(sharedRoomId)) {
+        roomIdInput.value = sharedRoomId;
+    }
     roomIdInput.addEventListener('keypress', function (event) {
         const isNumber = isFinite(event.key);
         if (!isNumber) {